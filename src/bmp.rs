@@ -0,0 +1,236 @@
+use crate::error::ImpassError;
+
+/// The signature every BMP file starts with.
+pub static SIGNATURE: [u8; 2] = *b"BM";
+
+/// Identifies impass payloads following the pixel array.
+pub static PAYLOAD_ID: &[u8; 7] = b"impass\0";
+
+/// Size of the file header, after which the DIB header starts.
+const FILE_HEADER_SIZE: usize = 14;
+/// Size of the BITMAPV5HEADER, the only DIB header
+/// which may point at data after the pixel array.
+const V5_HEADER_SIZE: usize = 124;
+
+/// BMP images, storing payloads right after their pixel array.
+pub struct Bmp;
+
+impl crate::carrier::Carrier for Bmp {
+    fn name(&self) -> &'static str {
+        "BMP"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&SIGNATURE)
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        Ok(find_payload(bytes)?.map(|range| bytes[range].to_vec()))
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        if u32::try_from(payload.len() + PAYLOAD_ID.len() + 4).is_err() {
+            return Err(ImpassError::TooLarge { limit: None });
+        }
+
+        let mut frame = PAYLOAD_ID.to_vec();
+        frame.extend((payload.len() as u32).to_le_bytes());
+        frame.extend(payload);
+
+        let end = pixels_end(bytes)?;
+        let mut embedded = bytes[..end].to_vec();
+        embedded.extend(&frame);
+        embedded.extend(&bytes[end..]);
+
+        moved(&mut embedded, end, frame.len() as i64);
+        Ok(embedded)
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let Some(range) = find_payload(bytes)? else {
+            return Ok(bytes.to_vec());
+        };
+
+        let frame = range.start - PAYLOAD_ID.len() - 4..range.end;
+        let mut stripped = bytes[..frame.start].to_vec();
+        stripped.extend(&bytes[frame.end..]);
+
+        moved(&mut stripped, frame.start, -(frame.len() as i64));
+        Ok(stripped)
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        pixels_end(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Finds where the pixel array of the BMP in `bytes` ends.
+pub fn pixels_end(bytes: &[u8]) -> Result<usize, ImpassError> {
+    if !bytes.starts_with(&SIGNATURE) {
+        return Err(ImpassError::invalid("BMP", "it has no BMP signature"));
+    }
+    if bytes.len() < FILE_HEADER_SIZE + 12 {
+        return Err(ImpassError::invalid("BMP", "it is truncated"));
+    }
+
+    let offset = read_u32(bytes, 10) as usize;
+    let header_size = read_u32(bytes, FILE_HEADER_SIZE) as usize;
+    let dib = &bytes[FILE_HEADER_SIZE..];
+
+    // The old OS/2 header only has 16-bit dimensions
+    let (width, height, bit_count, size) = if header_size == 12 {
+        let field = |at: usize| u16::from_le_bytes([dib[at], dib[at + 1]]) as usize;
+        (field(4), field(6), field(10), 0)
+    } else if header_size >= 40 && dib.len() >= header_size {
+        let field = |at: usize| read_u32(dib, at);
+        // Only the height may be negative, for top-down images
+        let Ok(width) = usize::try_from(field(4) as i32) else {
+            return Err(ImpassError::invalid("BMP", "its width is negative"));
+        };
+        (
+            width,
+            (field(8) as i32).unsigned_abs() as usize,
+            u16::from_le_bytes([dib[14], dib[15]]) as usize,
+            field(20) as usize,
+        )
+    } else {
+        return Err(ImpassError::invalid("BMP", "its header is corrupted"));
+    };
+    if width == 0 || height == 0 {
+        return Err(ImpassError::invalid("BMP", "its image has no pixels"));
+    }
+
+    // The size of the pixel array may be left out for uncompressed images
+    let size = if size != 0 {
+        Some(size)
+    } else {
+        width
+            .checked_mul(bit_count)
+            .and_then(|bits| (bits.div_ceil(32) * 4).checked_mul(height))
+    };
+
+    match size.and_then(|size| offset.checked_add(size)) {
+        Some(end) if end <= bytes.len() => Ok(end),
+        _ => Err(ImpassError::invalid("BMP", "it is truncated")),
+    }
+}
+
+/// Finds where the impass payload lives within the
+/// BMP in `bytes`: right after its pixel array.
+pub fn find_payload(bytes: &[u8]) -> Result<Option<std::ops::Range<usize>>, ImpassError> {
+    let end = pixels_end(bytes)?;
+    if !bytes[end..].starts_with(PAYLOAD_ID) {
+        return Ok(None);
+    }
+
+    let start = end + PAYLOAD_ID.len() + 4;
+    if bytes.len() < start {
+        return Err(ImpassError::Truncated);
+    }
+
+    let len = read_u32(bytes, start - 4) as usize;
+    if bytes.len() < start + len {
+        return Err(ImpassError::Truncated);
+    }
+    Ok(Some(start..start + len))
+}
+
+/// Fixes up the headers of the BMP in `bytes` after `by` bytes were
+/// inserted (or removed) `at` the given position: its file size and,
+/// for V5 headers, the position of an ICC profile following it.
+/// The whole DIB header has to be there, as `pixels_end` makes sure.
+fn moved(bytes: &mut [u8], at: usize, by: i64) {
+    let size = bytes.len() as u32;
+    bytes[2..6].copy_from_slice(&size.to_le_bytes());
+
+    if read_u32(bytes, FILE_HEADER_SIZE) as usize >= V5_HEADER_SIZE {
+        let field = FILE_HEADER_SIZE + 112;
+        let profile = read_u32(bytes, field) as i64;
+        if profile != 0 && FILE_HEADER_SIZE as i64 + profile >= at as i64 {
+            bytes[field..field + 4].copy_from_slice(&((profile + by) as u32).to_le_bytes());
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::carrier::Carrier;
+
+    #[test]
+    fn finds_the_pixel_array() {
+        let bytes = std::fs::read("images/gradient.bmp").unwrap();
+
+        assert_eq!(super::pixels_end(&bytes), Ok(bytes.len()));
+    }
+
+    #[test]
+    fn plain_images_have_no_payload() {
+        let bytes = std::fs::read("images/gradient.bmp").unwrap();
+
+        assert_eq!(super::Bmp.extract(&bytes), Ok(None));
+    }
+
+    #[test]
+    fn embeds_and_strips_payloads() {
+        let bytes = std::fs::read("images/gradient.bmp").unwrap();
+
+        let embedded = super::Bmp.embed(&bytes, b"payload").unwrap();
+
+        assert_eq!(super::Bmp.extract(&embedded), Ok(Some(b"payload".to_vec())));
+        assert_eq!(super::read_u32(&embedded, 2) as usize, embedded.len());
+        assert_eq!(super::Bmp.strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn rejects_crafted_headers() {
+        let bytes = std::fs::read("images/gradient.bmp").unwrap();
+
+        let mut negative = bytes.clone();
+        negative[super::FILE_HEADER_SIZE + 4..super::FILE_HEADER_SIZE + 8]
+            .copy_from_slice(&(-5i32).to_le_bytes());
+        assert_eq!(
+            super::pixels_end(&negative),
+            Err(crate::error::ImpassError::invalid(
+                "BMP",
+                "its width is negative"
+            ))
+        );
+
+        for at in [4, 8] {
+            let mut empty = bytes.clone();
+            empty[super::FILE_HEADER_SIZE + at..super::FILE_HEADER_SIZE + at + 4].fill(0);
+            assert_eq!(
+                super::pixels_end(&empty),
+                Err(crate::error::ImpassError::invalid(
+                    "BMP",
+                    "its image has no pixels"
+                ))
+            );
+        }
+
+        let mut huge = bytes.clone();
+        huge[super::FILE_HEADER_SIZE + 4..super::FILE_HEADER_SIZE + 8]
+            .copy_from_slice(&i32::MAX.to_le_bytes());
+        huge[super::FILE_HEADER_SIZE + 20..super::FILE_HEADER_SIZE + 24].fill(0);
+        assert!(super::pixels_end(&huge).is_err());
+
+        // A V5 header cut short, its ICC profile field missing
+        let mut truncated = bytes[..super::FILE_HEADER_SIZE + 100].to_vec();
+        truncated[super::FILE_HEADER_SIZE..super::FILE_HEADER_SIZE + 4]
+            .copy_from_slice(&(super::V5_HEADER_SIZE as u32).to_le_bytes());
+        assert!(super::Bmp.embed(&truncated, b"payload").is_err());
+        assert!(super::Bmp.strip(&truncated).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "File is not a valid BMP: it is truncated!")]
+    fn rejects_truncated_images() {
+        let bytes = std::fs::read("images/gradient.bmp").unwrap();
+
+        crate::or_exit!(super::pixels_end(&bytes[..bytes.len() - 2]));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::ImpassError;
+
+/// A file format (image or audio) impass payloads can be stored in. Adding
+/// a format only takes implementing this trait and listing it in `CARRIERS`.
+pub trait Carrier: Sync {
+    /// The name of the format, as shown to the user.
+    fn name(&self) -> &'static str;
+
+    /// Whether `bytes` are a file of this format, going by its signature.
+    fn detect(&self, bytes: &[u8]) -> bool;
+
+    /// Extracts the impass payload stored as-is within `bytes`.
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError>;
+
+    /// Stores the `payload` as-is within `bytes`.
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError>;
+
+    /// Removes every impass payload stored as-is within `bytes`.
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError>;
+
+    /// The position where the data of the file ends within `bytes`.
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError>;
+
+    /// Whether payloads can be hidden within the samples
+    /// (such as pixels) of the file in `bytes`.
+    fn can_hide(&self, _bytes: &[u8]) -> bool {
+        false
+    }
+
+    /// Reveals the payload hidden within the samples of `bytes` in the
+    /// order shuffled with any of the given `keys`, alongside the index
+    /// of the key it was hidden with.
+    fn reveal(&self, _bytes: &[u8], _keys: &[&[u8]]) -> Option<(usize, Vec<u8>)> {
+        None
+    }
+
+    /// Hides the `payload` within the samples of `bytes`
+    /// in the order shuffled with the given `key`.
+    fn hide(&self, _bytes: &[u8], _payload: &[u8], _key: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        Err(ImpassError::CannotHide {
+            format: self.name(),
+            reason: None,
+        })
+    }
+}
+
+/// Every supported format, in the order they are detected in.
+/// BMP goes last, having the weakest signature.
+static CARRIERS: &[&dyn Carrier] = &[
+    &crate::png::Png,
+    &crate::jpeg::Jpeg,
+    &crate::webp::Webp,
+    &crate::gif::Gif,
+    &crate::wav::Wav,
+    &crate::bmp::Bmp,
+];
+
+/// Detects the format of the file in `bytes`.
+pub fn detect(bytes: &[u8]) -> Option<&'static dyn Carrier> {
+    CARRIERS
+        .iter()
+        .copied()
+        .find(|carrier| carrier.detect(bytes))
+}
+
+/// The format of the file in `bytes`, erroring if it is not supported.
+pub fn of(bytes: &[u8]) -> Result<&'static dyn Carrier, ImpassError> {
+    detect(bytes).ok_or(ImpassError::UnsupportedFormat)
+}
+
+/// The names of all supported formats.
+pub fn supported() -> String {
+    CARRIERS
+        .iter()
+        .map(|carrier| carrier.name())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Where an impass payload was found within a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    /// Stored as-is, see `Carrier::embed`.
+    Embedded,
+    /// Hidden within the least significant bits of the samples
+    /// (such as pixels), in the order shuffled with the given key.
+    Hidden(Vec<u8>),
+}
+
+mod test {
+    #[test]
+    fn detects_formats() {
+        for (file, name) in [
+            ("images/cat.png", "PNG"),
+            ("images/kekw.jpg", "JPEG"),
+            ("images/pixel.webp", "WebP"),
+            ("images/stripes.gif", "GIF"),
+            ("images/gradient.bmp", "BMP"),
+            ("audio/beep.wav", "WAV"),
+        ] {
+            let bytes = std::fs::read(file).unwrap();
+            assert_eq!(super::of(&bytes).unwrap().name(), name);
+        }
+
+        assert!(super::detect(b"plain text").is_none());
+    }
+
+    #[test]
+    fn only_some_formats_can_hide() {
+        let png = std::fs::read("images/gradient.png").unwrap();
+        let wav = std::fs::read("audio/beep.wav").unwrap();
+        let jpeg = std::fs::read("images/kekw.jpg").unwrap();
+
+        assert!(super::of(&png).unwrap().can_hide(&png));
+        assert!(super::of(&wav).unwrap().can_hide(&wav));
+        assert!(!super::of(&jpeg).unwrap().can_hide(&jpeg));
+    }
+
+    #[test]
+    #[should_panic(expected = "Secrets cannot be hidden within the contents of JPEG files!")]
+    fn refuses_to_hide_in_unsupported_formats() {
+        let jpeg = std::fs::read("images/kekw.jpg").unwrap();
+
+        crate::or_exit!(super::of(&jpeg).unwrap().hide(&jpeg, b"payload", b"key"));
+    }
+}
@@ -0,0 +1,93 @@
+//! The command-line interface over the library. Unlike the library,
+//! it reports failures and exits the process.
+
+use crate::{config, impass, reading, utils};
+
+/// Runs impass with the arguments and environment of the process.
+pub fn run() {
+    dotenv::dotenv().ok();
+
+    let env = std::env::vars().collect::<std::collections::HashMap<String, String>>();
+    let argv: Vec<String> = std::env::args().collect();
+
+    // Use the commented env for generating test files:
+    let simplified_env = utils::env::collect_env(env); // utils::env::collect_env(utils::Env::new());
+    let mut config = config::Config::from_args(&argv, simplified_env);
+
+    if config.rotate.is_some() {
+        let report = impass::rotate::rotate(&config);
+        report.print();
+        if !report.failed.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut content = Vec::new();
+    reading::gimme_bytecode(&config, &mut content);
+
+    if config.info {
+        impass::inspect::print(&config, &content);
+    } else if let Some(operation) = config.vault.clone() {
+        use impass::vault::{self, Operation};
+
+        match operation {
+            Operation::Add(name) => {
+                let input = read_secret(&config);
+                // The first secret picks the password, like encoding does
+                if config.password.is_none() && !impass::decoder::holds_secrets(&config, &content) {
+                    if let Some(pass) = utils::prompt_new_password() {
+                        config.set_password(pass);
+                    }
+                }
+                vault::add(&config, &mut content, &name, &input);
+            }
+            Operation::Update(name) => {
+                let input = read_secret(&config);
+                vault::update(&config, &mut content, &name, &input);
+            }
+            Operation::Remove(name) => vault::remove(&config, &mut content, &name),
+            Operation::Get(name) => {
+                let secret = vault::get(&config, &mut content, &name);
+                utils::show_secret(config.reveal, &secret);
+            }
+            Operation::List => {
+                for name in vault::list(&config, &mut content) {
+                    println!("{name}");
+                }
+            }
+        }
+    } else if config.strip {
+        impass::strip::strip(&config, &mut content);
+    } else if config.mode == config::Mode::File || config.mode == config::Mode::Write {
+        // We'll encode something, so get a secret
+        let input = read_secret(&config);
+        if config.password.is_none() {
+            if let Some(pass) = utils::prompt_new_password() {
+                config.set_password(pass);
+            }
+        }
+
+        impass::encoder::encode(&config, &mut content, &input);
+        crate::info!(format!(
+            "Encoded into {}, using contents from {}.",
+            config.output_file,
+            config.file_to_read().clone().unwrap()
+        ));
+    } else {
+        impass::decoder::decode(&config, &mut content, true);
+    }
+}
+
+/// Reads the secret from where the user asked to, asking for it otherwise.
+fn read_secret(config: &config::Config) -> Vec<u8> {
+    use utils::source::Source;
+
+    match (&config.secret_from, &config.pass_from) {
+        (Some(source), _) => source.read(),
+        (None, Some(Source::Stdin)) => crate::error!(
+            "The secret cannot be asked for once the password was read from stdin, pass it with --secret-from instead!"
+        ),
+        (None, _) => utils::prompt("Enter your secret").into_bytes(),
+    }
+}
@@ -1,8 +1,9 @@
+use crate::impass::vault;
 use crate::{error, utils};
 
 /// The mode in which the config will work for
 /// the current run.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum Mode {
     Write,
     Read,
@@ -15,7 +16,7 @@ pub enum Mode {
 /// run. Exposes a password, mode, output
 /// file and environment to be propagated and used
 /// during runtime for both encryption/decription.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Config {
     read_file: Option<String>,
     write_file: Option<String>,
@@ -24,6 +25,38 @@ pub struct Config {
     pub mode: Mode,
     pub output_file: String,
     pub env: std::collections::HashMap<String, String>,
+    pub vault: Option<crate::impass::vault::Operation>,
+    /// Failed password attempts allowed before the encoded image
+    /// destroys itself, overriding MAX_ATTEMPTS from the env.
+    pub max_attempts: Option<u8>,
+    /// Whether to prompt for the password again after a wrong one.
+    pub retry: bool,
+    /// Whether to hide the payload within the samples (such as pixels) of the file
+    /// instead of a chunk of its own.
+    pub lsb: bool,
+    /// Whether to remove the secrets from the file, restoring it as it was.
+    pub strip: bool,
+    /// Whether to unlock the secrets with their password before stripping them.
+    pub verify: bool,
+    /// Whether to report on the payload of the file instead of decoding it.
+    pub info: bool,
+    /// Whether to overwrite the secrets the file already holds when encoding.
+    pub replace: bool,
+    /// Whether to print decoded secrets, which are redacted otherwise.
+    pub reveal: bool,
+    /// A directory of files to re-encrypt under new env parameters,
+    /// read from the `rotate_from` env file and the `rotate_to` one
+    /// (the current env, if not given).
+    pub rotate: Option<String>,
+    pub rotate_from: Option<String>,
+    pub rotate_to: Option<String>,
+    /// Whether rotating skips files which could hide secrets within their
+    /// samples, yet none were found there. They fail the rotation otherwise.
+    pub skip_plain: bool,
+    /// Where to read the secret from instead of asking for it.
+    pub secret_from: Option<utils::source::Source>,
+    /// Where to read the password from instead of asking for it.
+    pub pass_from: Option<utils::source::Source>,
 }
 
 impl Config {
@@ -47,7 +80,7 @@ impl Config {
     /// Sets the output file by modifying self.
     /// Useful as an accessor down the work process.
     fn set_output(mut self) -> Self {
-        if !self.output_file.is_empty() {
+        if !self.output_file.is_empty() || self.rotate.is_some() {
             return self;
         }
 
@@ -80,6 +113,24 @@ impl Config {
     /// Checks if configuration is valid before
     /// allowing it to propagate in runtime.
     fn checked(self) -> Self {
+        if let Some(dir) = &self.rotate {
+            if !std::path::Path::new(dir).is_dir() {
+                error!(format!(
+                    "The given directory to rotate \"{dir}\" does not exist!"
+                ));
+            }
+            if self.rotate_from.is_none() {
+                error!("The env file the files were encoded with must be provided with --from!");
+            }
+            for env_file in [&self.rotate_from, &self.rotate_to].into_iter().flatten() {
+                if !std::path::Path::new(env_file).exists() {
+                    error!(format!("The given env file \"{env_file}\" does not exist!"));
+                }
+            }
+
+            return self;
+        }
+
         if self.mode == Mode::Write && self.file_to_read().is_none() {
             error!("A file name to write (output) was provided, but no file to use was given");
         } // we good with mode
@@ -93,6 +144,48 @@ impl Config {
         self
     }
 
+    /// The number of failed password attempts newly encoded
+    /// images allow before destroying themselves, 0 meaning unlimited.
+    pub fn attempts_limit(&self) -> u8 {
+        self.max_attempts.unwrap_or_else(|| {
+            self.env
+                .get("max_attempts")
+                .and_then(|max| max.parse().ok())
+                .unwrap_or(0)
+        })
+    }
+
+    /// The library options matching this run.
+    pub fn options(&self) -> crate::Options {
+        crate::Options {
+            password: self.password.clone(),
+            env: self.env.clone(),
+            max_attempts: self.attempts_limit(),
+            lsb: self.lsb,
+            replace: self.replace,
+        }
+    }
+
+    /// Reads the password from where the user asked to, so that it
+    /// never gets asked for. Either -p or a source can be given.
+    fn read_sources(mut self) -> Self {
+        let Some(source) = &self.pass_from else {
+            return self;
+        };
+        if self.password.is_some() {
+            error!("Pass the password either with -p or with --pass-from, not both!");
+        }
+        if self.secret_from == Some(utils::source::Source::Stdin)
+            && *source == utils::source::Source::Stdin
+        {
+            error!("The secret and the password cannot both be read from stdin!");
+        }
+
+        let pass = source.read_password();
+        self.set_password(pass);
+        self
+    }
+
     /// Sets a password for self.
     pub fn set_password(&mut self, pass: String) {
         self.password = Some(pass);
@@ -100,13 +193,36 @@ impl Config {
 
     /// A help message.
     pub fn print_help(&self) {
+        println!("Usage: impass COMMAND [OPTIONS]\n\nWhere available commands are:");
+        for command in COMMANDS {
+            println!("\t{:<20}\t{}", command.usage(), command.about);
+        }
         println!(
-            "Usage: impass [OPTIONS]\n
-Where available options are:
+            "\nRun `impass COMMAND --help` to see the options of a command.\n
+Deprecated, the options used without a command:
 \t-i, --input \tSet an input file
 \t-o, --output\tSet an output file
 \t-f, --file  \tSet a file to read or write
 \t-p, --pass  \tSet a password to protect your file
+\t--pass-from SOURCE  \tRead the password from stdin, file:PATH, fd:N or env:NAME
+\t--secret-from SOURCE\tRead the secret from stdin, file:PATH, fd:N or env:NAME
+\t--tries N    \tDestroy the encoded file after N wrong passwords
+\t--retry      \tAsk for the password again after a wrong one
+\t--replace    \tOverwrite the secrets the file already holds
+\t--lsb        \tHide the secrets within the pixels (or audio samples) of the file, or look for them there
+\t--add NAME   \tAdd a named secret to the image
+\t--get NAME   \tShow the named secret stored in the image
+\t--reveal     \tPrint decoded secrets (alone, to stdout) instead of hiding them
+\t--update NAME\tChange the named secret stored in the image
+\t--remove NAME\tRemove the named secret from the image
+\t--list       \tList the names of all secrets in the image
+\t--info       \tShow what the file holds, without asking for a password
+\t--strip      \tRemove the secrets, restoring the file as it was before
+\t--verify     \tRequire the password before stripping the secrets
+\t--rotate DIR \tRe-encrypt every file in the directory under new env parameters
+\t--from FILE  \tThe env file the files to rotate were encoded with
+\t--to FILE    \tThe env file to rotate them to (your current one by default)
+\t--skip-plain \tSkip the files to rotate in which no secrets were found, instead of failing
 \t--create-env OR    | Recreates your environment file
 \t    --generate-env | populating it with pseudo-random values
 "
@@ -121,80 +237,267 @@ Where available options are:
             error!("An argument for image must be provided!");
         }
 
+        if let Some(command) = COMMANDS.iter().find(|command| command.name == argv[1]) {
+            return Self::from_command(command, argv, env);
+        }
+        if argv[1] == "help" {
+            match COMMANDS
+                .iter()
+                .find(|command| Some(&command.name.to_string()) == argv.get(2))
+            {
+                Some(command) => command.print_help(),
+                None => Config::default().print_help(),
+            }
+            std::process::exit(0);
+        }
+
         let mut idx = 1; // skip program name
         let mut config = Config::default();
 
         while idx < argv.len() {
-            match argv[idx].as_str() {
-                "-o" | "--output" => {
-                    if let Some(out_file) = argv.get(idx + 1) {
-                        idx += 1;
-                        config.write_file = Some(out_file.to_string());
-                    } else {
-                        error!(format!(
-                            "File name must be provided after the {} flag!",
-                            argv[idx]
-                        ));
-                    }
+            idx = config.parse_option(argv, idx, &env) + 1;
+        }
+
+        // The mode gets guessed from the file flags given
+        if config.read_file.is_some() || config.file.is_some() || config.write_file.is_some() {
+            crate::info!("Using impass without a command is deprecated, see `impass --help` for the commands to use instead.");
+        }
+
+        config
+            .set_mode()
+            .set_output()
+            .with_env(env)
+            .checked()
+            .read_sources()
+    }
+
+    /// Constructs a Self from a list of args starting with a `command`,
+    /// which only takes its own arguments and options.
+    fn from_command(command: &Command, argv: &[String], env: utils::Env) -> Config {
+        let mut config = Config::default();
+        let mut args = Vec::new();
+
+        let mut idx = 2; // skip program and command names
+        while idx < argv.len() {
+            let arg = argv[idx].as_str();
+            if arg == "-h" || arg == "--help" {
+                command.print_help();
+                std::process::exit(0);
+            }
+
+            if !arg.starts_with('-') {
+                args.push(arg.to_string());
+            } else if command.takes(arg) {
+                idx = config.parse_option(argv, idx, &env);
+            } else {
+                command.print_help();
+                error!(format!(
+                    "Unrecognized option or flag {arg} for the {} command",
+                    command.name
+                ));
+            }
+            idx += 1;
+        }
+
+        if args.len() != command.args.len() {
+            command.print_help();
+            error!(format!("Usage: impass {}", command.usage()));
+        }
+
+        let file = Some(args[0].clone());
+        let name = args.get(1).cloned().unwrap_or_default();
+        match command.name {
+            "encode" => config.file = file,
+            "decode" => config.read_file = file,
+            "inspect" => {
+                config.read_file = file;
+                config.info = true;
+            }
+            "strip" => {
+                config.read_file = file;
+                config.strip = true;
+            }
+            "list" => {
+                config.read_file = file;
+                config.vault = Some(vault::Operation::List);
+            }
+            "get" => {
+                config.read_file = file;
+                config.vault = Some(vault::Operation::Get(name));
+            }
+            "add" | "update" | "remove" => {
+                config.file = file;
+                config.vault = Some(match command.name {
+                    "add" => vault::Operation::Add(name),
+                    "update" => vault::Operation::Update(name),
+                    _ => vault::Operation::Remove(name),
+                });
+            }
+            "rotate" => config.rotate = file,
+            "env" => {
+                if args[0] != "generate" {
+                    command.print_help();
+                    error!(format!(
+                        "Unknown env action {}, use: impass env generate",
+                        args[0]
+                    ));
                 }
-                "-i" | "--input" => {
-                    if let Some(in_file) = argv.get(idx + 1) {
-                        idx += 1;
-                        config.read_file = Some(in_file.to_string());
-                    } else {
-                        error!(format!(
-                            "File name must be provided after the {} flag!",
-                            argv[idx]
-                        ));
-                    }
+                regenerate_env(&env);
+                std::process::exit(0);
+            }
+            _ => crate::unreachable!(format!("Command {} not handled", command.name)),
+        }
+
+        config
+            .set_mode()
+            .set_output()
+            .with_env(env)
+            .checked()
+            .read_sources()
+    }
+
+    /// Applies the option at `idx` of the `argv`, yielding
+    /// the index of the last argument it took.
+    fn parse_option(&mut self, argv: &[String], mut idx: usize, env: &utils::Env) -> usize {
+        match argv[idx].as_str() {
+            "-o" | "--output" => {
+                if let Some(out_file) = argv.get(idx + 1) {
+                    idx += 1;
+                    self.write_file = Some(out_file.to_string());
+                } else {
+                    error!(format!(
+                        "File name must be provided after the {} flag!",
+                        argv[idx]
+                    ));
                 }
-                "-f" | "--file" => {
-                    if let Some(write_file) = argv.get(idx + 1) {
-                        idx += 1;
-                        config.file = Some(write_file.to_string());
-                    } else {
-                        error!(format!(
-                            "File name must be provided after the {} flag!",
-                            argv[idx]
-                        ));
-                    }
+            }
+            "-i" | "--input" => {
+                if let Some(in_file) = argv.get(idx + 1) {
+                    idx += 1;
+                    self.read_file = Some(in_file.to_string());
+                } else {
+                    error!(format!(
+                        "File name must be provided after the {} flag!",
+                        argv[idx]
+                    ));
                 }
-                "-p" | "--pass" => {
-                    if let Some(pass) = argv.get(idx + 1) {
-                        idx += 1;
-                        config.set_password(pass.clone());
-                    }
+            }
+            "-f" | "--file" => {
+                if let Some(write_file) = argv.get(idx + 1) {
+                    idx += 1;
+                    self.file = Some(write_file.to_string());
+                } else {
+                    error!(format!(
+                        "File name must be provided after the {} flag!",
+                        argv[idx]
+                    ));
+                }
+            }
+            "-p" | "--pass" => {
+                if let Some(pass) = argv.get(idx + 1) {
+                    idx += 1;
+                    self.set_password(pass.clone());
                 }
-                "-h" | "--help" => {
-                    config.print_help();
-                    std::process::exit(0);
+            }
+            "--secret-from" | "--pass-from" => {
+                let Some(Some(source)) = argv.get(idx + 1).map(|s| utils::source::Source::parse(s))
+                else {
+                    error!(format!(
+                        "A source (stdin, file:PATH, fd:N or env:NAME) must be provided after the {} flag!",
+                        argv[idx]
+                    ));
+                };
+                idx += 1;
+                if argv[idx - 1] == "--secret-from" {
+                    self.secret_from = Some(source);
+                } else {
+                    self.pass_from = Some(source);
                 }
-                "--create-env" | "--generate-env" => {
+            }
+            "--add" | "--get" | "--update" | "--remove" => {
+                if let Some(name) = argv.get(idx + 1) {
+                    idx += 1;
+                    self.vault = Some(match argv[idx - 1].as_str() {
+                        "--add" => vault::Operation::Add(name.clone()),
+                        "--get" => vault::Operation::Get(name.clone()),
+                        "--update" => vault::Operation::Update(name.clone()),
+                        _ => vault::Operation::Remove(name.clone()),
+                    });
+                } else {
+                    error!(format!(
+                        "Entry name must be provided after the {} flag!",
+                        argv[idx]
+                    ));
+                }
+            }
+            "--rotate" | "--from" | "--to" => {
+                if let Some(path) = argv.get(idx + 1) {
                     idx += 1;
-                    if std::path::Path::new(".env").exists() {
-                        let ans = utils::prompt(
-                            "Configuration file already exists on your machine. Regenerate it?",
-                        );
-
-                        let possible_answers = env["answers"].split(',').collect::<Vec<&str>>();
-                        if possible_answers.contains(&ans.to_lowercase().as_str()) {
-                            if crate::utils::env::generate_env().is_err() {
-                                error!("Could not generate a config properly!");
-                            } else {
-                                crate::info!("Config was generated successfully!");
-                            }
-                        }
+                    let path = Some(path.to_string());
+                    match argv[idx - 1].as_str() {
+                        "--rotate" => self.rotate = path,
+                        "--from" => self.rotate_from = path,
+                        _ => self.rotate_to = path,
                     }
+                } else {
+                    error!(format!(
+                        "A path must be provided after the {} flag!",
+                        argv[idx]
+                    ));
                 }
-                _ => {
-                    config.print_help();
-                    error!(format!("Unrecognized option or flag {}", argv[idx]));
+            }
+            "--tries" => {
+                if let Some(Ok(tries)) = argv.get(idx + 1).map(|n| n.parse::<u8>()) {
+                    idx += 1;
+                    self.max_attempts = Some(tries);
+                } else {
+                    error!(format!(
+                        "A number between 0 and 255 must be provided after the {} flag!",
+                        argv[idx]
+                    ));
                 }
             }
-            idx += 1;
+            "--retry" => {
+                self.retry = true;
+            }
+            "--replace" => {
+                self.replace = true;
+            }
+            "--reveal" => {
+                self.reveal = true;
+            }
+            "--lsb" => {
+                self.lsb = true;
+            }
+            "--info" => {
+                self.info = true;
+            }
+            "--strip" => {
+                self.strip = true;
+            }
+            "--verify" => {
+                self.verify = true;
+            }
+            "--skip-plain" => {
+                self.skip_plain = true;
+            }
+            "--list" => {
+                self.vault = Some(vault::Operation::List);
+            }
+            "-h" | "--help" => {
+                self.print_help();
+                std::process::exit(0);
+            }
+            "--create-env" | "--generate-env" => {
+                regenerate_env(env);
+            }
+            _ => {
+                self.print_help();
+                error!(format!("Unrecognized option or flag {}", argv[idx]));
+            }
         }
 
-        config.set_mode().set_output().with_env(env).checked()
+        idx
     }
 
     #[allow(dead_code)]
@@ -219,6 +522,21 @@ Where available options are:
             output_file,
             password,
             env: crate::utils::Env::new(),
+            vault: None,
+            max_attempts: None,
+            retry: false,
+            lsb: false,
+            strip: false,
+            verify: false,
+            info: false,
+            replace: false,
+            reveal: false,
+            rotate: None,
+            rotate_from: None,
+            rotate_to: None,
+            skip_plain: false,
+            secret_from: None,
+            pass_from: None,
         }
         .set_mode()
         .set_output()
@@ -236,6 +554,250 @@ Where available options are:
     }
 }
 
+/// A subcommand of the CLI, along with the arguments and options it takes.
+pub struct Command {
+    pub name: &'static str,
+    pub args: &'static [&'static str],
+    /// The long names of the options it takes, see `OPTIONS`.
+    pub options: &'static [&'static str],
+    pub about: &'static str,
+}
+
+/// An option of the CLI, with its names and what it does.
+struct Opt {
+    names: &'static [&'static str],
+    usage: &'static str,
+    about: &'static str,
+}
+
+/// Every subcommand of the CLI.
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: "encode",
+        args: &["FILE"],
+        options: &[
+            "--output",
+            "--pass",
+            "--pass-from",
+            "--secret-from",
+            "--tries",
+            "--lsb",
+            "--replace",
+        ],
+        about: "Encode a secret into the file",
+    },
+    Command {
+        name: "decode",
+        args: &["FILE"],
+        options: &["--pass", "--pass-from", "--lsb", "--retry", "--reveal"],
+        about: "Show the secret stored in the file",
+    },
+    Command {
+        name: "inspect",
+        args: &["FILE"],
+        options: &["--pass", "--pass-from", "--lsb"],
+        about: "Show what the file holds, without asking for a password",
+    },
+    Command {
+        name: "strip",
+        args: &["FILE"],
+        options: &[
+            "--output",
+            "--pass",
+            "--pass-from",
+            "--lsb",
+            "--verify",
+            "--retry",
+        ],
+        about: "Remove the secrets, restoring the file as it was before",
+    },
+    Command {
+        name: "list",
+        args: &["FILE"],
+        options: &["--pass", "--pass-from", "--lsb", "--retry"],
+        about: "List the names of all secrets in the file",
+    },
+    Command {
+        name: "get",
+        args: &["FILE", "NAME"],
+        options: &["--pass", "--pass-from", "--lsb", "--retry", "--reveal"],
+        about: "Show the named secret stored in the file",
+    },
+    Command {
+        name: "add",
+        args: &["FILE", "NAME"],
+        options: &[
+            "--output",
+            "--pass",
+            "--pass-from",
+            "--secret-from",
+            "--tries",
+            "--lsb",
+            "--replace",
+            "--retry",
+        ],
+        about: "Add a named secret to the file",
+    },
+    Command {
+        name: "update",
+        args: &["FILE", "NAME"],
+        options: &[
+            "--output",
+            "--pass",
+            "--pass-from",
+            "--secret-from",
+            "--lsb",
+            "--retry",
+        ],
+        about: "Change the named secret stored in the file",
+    },
+    Command {
+        name: "remove",
+        args: &["FILE", "NAME"],
+        options: &["--output", "--pass", "--pass-from", "--lsb", "--retry"],
+        about: "Remove the named secret from the file",
+    },
+    Command {
+        name: "rotate",
+        args: &["DIR"],
+        options: &[
+            "--from",
+            "--to",
+            "--pass",
+            "--pass-from",
+            "--lsb",
+            "--retry",
+            "--skip-plain",
+        ],
+        about: "Re-encrypt every file in the directory under new env parameters",
+    },
+    Command {
+        name: "env",
+        args: &["generate"],
+        options: &[],
+        about: "Recreate your environment file, populating it with pseudo-random values",
+    },
+];
+
+/// Every option subcommands can take.
+static OPTIONS: &[Opt] = &[
+    Opt {
+        names: &["-o", "--output"],
+        usage: "-o, --output FILE",
+        about: "Set an output file",
+    },
+    Opt {
+        names: &["-p", "--pass"],
+        usage: "-p, --pass PASS",
+        about: "Set a password to protect (or unlock) your file, asked for when omitted",
+    },
+    Opt {
+        names: &["--pass-from"],
+        usage: "--pass-from SOURCE",
+        about: "Read the password from stdin, file:PATH, fd:N or env:NAME instead",
+    },
+    Opt {
+        names: &["--secret-from"],
+        usage: "--secret-from SOURCE",
+        about: "Read the secret from stdin, file:PATH, fd:N or env:NAME instead",
+    },
+    Opt {
+        names: &["--tries"],
+        usage: "--tries N",
+        about: "Destroy the encoded file after N wrong passwords",
+    },
+    Opt {
+        names: &["--retry"],
+        usage: "--retry",
+        about: "Ask for the password again after a wrong one",
+    },
+    Opt {
+        names: &["--reveal"],
+        usage: "--reveal",
+        about: "Print the secret (alone, to stdout) instead of hiding it",
+    },
+    Opt {
+        names: &["--replace"],
+        usage: "--replace",
+        about: "Overwrite the secrets the file already holds",
+    },
+    Opt {
+        names: &["--lsb"],
+        usage: "--lsb",
+        about: "Hide the secrets within the pixels (or audio samples) of the file, or look for them there",
+    },
+    Opt {
+        names: &["--verify"],
+        usage: "--verify",
+        about: "Require the password before stripping the secrets",
+    },
+    Opt {
+        names: &["--from"],
+        usage: "--from FILE",
+        about: "The env file the files to rotate were encoded with",
+    },
+    Opt {
+        names: &["--to"],
+        usage: "--to FILE",
+        about: "The env file to rotate them to (your current one by default)",
+    },
+    Opt {
+        names: &["--skip-plain"],
+        usage: "--skip-plain",
+        about: "Skip the files in which no secrets were found, instead of failing",
+    },
+];
+
+impl Command {
+    /// How the command is used, as shown in help messages.
+    pub fn usage(&self) -> String {
+        [&[self.name], self.args].concat().join(" ")
+    }
+
+    /// Whether the command takes the option named `arg`.
+    fn takes(&self, arg: &str) -> bool {
+        OPTIONS
+            .iter()
+            .any(|opt| opt.names.contains(&arg) && self.options.contains(opt.names.last().unwrap()))
+    }
+
+    /// A help message for the command.
+    pub fn print_help(&self) {
+        println!("Usage: impass {} [OPTIONS]\n\n{}", self.usage(), self.about);
+        if self.options.is_empty() {
+            return;
+        }
+
+        println!("\nWhere available options are:");
+        for opt in OPTIONS {
+            if self.options.contains(opt.names.last().unwrap()) {
+                println!("\t{:<18}\t{}", opt.usage, opt.about);
+            }
+        }
+    }
+}
+
+/// Generates a new environment file, asking first whether to
+/// overwrite the existing one, or to create one if there is none.
+fn regenerate_env(env: &utils::Env) {
+    let ans = if std::path::Path::new(".env").exists() {
+        utils::prompt("Configuration file already exists on your machine. Regenerate it?")
+    } else {
+        utils::prompt("No configuration file on your machine. Generate one?")
+    };
+
+    let possible_answers = env["answers"].split(',').collect::<Vec<&str>>();
+    if !possible_answers.contains(&ans.to_lowercase().as_str()) {
+        return;
+    }
+
+    if crate::utils::env::generate_env().is_err() {
+        error!("Could not generate a config properly!");
+    } else {
+        crate::info!("Config was generated successfully!");
+    }
+}
+
 mod test {
     mod config {
         #[test]
@@ -399,6 +961,310 @@ mod test {
             );
         }
 
+        #[test]
+        fn can_create_config_with_vault_operation() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-f".to_string(),
+                    "images/cat.png".to_string(),
+                    "--add".to_string(),
+                    "github".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert_eq!(
+                cfg.vault,
+                Some(super::super::vault::Operation::Add("github".to_string()))
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Entry name must be provided after the --get flag!")]
+        fn test_invalid_get_flag() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-i".to_string(),
+                    "images/cat.png".to_string(),
+                    "--get".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        fn can_create_config_with_attempt_limit() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-f".to_string(),
+                    "images/cat.png".to_string(),
+                    "--tries".to_string(),
+                    "3".to_string(),
+                    "--retry".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert_eq!(cfg.attempts_limit(), 3);
+            assert!(cfg.retry);
+        }
+
+        #[test]
+        fn can_create_config_with_lsb() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-f".to_string(),
+                    "images/gradient.png".to_string(),
+                    "--lsb".to_string(),
+                    "--replace".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert!(cfg.lsb);
+            assert!(cfg.replace);
+        }
+
+        #[test]
+        fn can_create_config_with_strip() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-i".to_string(),
+                    "images/cat.png".to_string(),
+                    "--strip".to_string(),
+                    "--verify".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert!(cfg.strip);
+            assert!(cfg.verify);
+            assert_eq!(cfg.output_file, "result/cat.png".to_owned());
+        }
+
+        #[test]
+        fn can_create_config_with_rotation() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "--rotate".to_string(),
+                    "images".to_string(),
+                    "--from".to_string(),
+                    "README.md".to_string(),
+                    "--skip-plain".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert_eq!(cfg.rotate, Some("images".to_string()));
+            assert_eq!(cfg.rotate_from, Some("README.md".to_string()));
+            assert_eq!(cfg.rotate_to, None);
+            assert!(cfg.skip_plain);
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "The env file the files were encoded with must be provided with --from!"
+        )]
+        fn cant_rotate_without_old_env() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "--rotate".to_string(),
+                    "images".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        fn can_create_config_with_info() {
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-i".to_string(),
+                    "images/cat.png".to_string(),
+                    "--info".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert!(cfg.info);
+            assert!(!cfg.strip);
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "A number between 0 and 255 must be provided after the --tries flag!"
+        )]
+        fn test_invalid_tries_flag() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "-f".to_string(),
+                    "images/cat.png".to_string(),
+                    "--tries".to_string(),
+                    "many".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        fn can_create_config_from_commands() {
+            let env = super::super::utils::env::collect_env(super::super::utils::Env::new());
+            let args = |args: &[&str]| {
+                // "--" is needed to distinguish the initial arg (program name)
+                [&["--"], args]
+                    .concat()
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+            };
+
+            let cfg = super::super::Config::from_args(
+                &args(&["encode", "images/cat.png", "-o", "out/output.png", "--lsb"]),
+                env.clone(),
+            );
+            assert_eq!(cfg.mode, super::super::Mode::File);
+            assert_eq!(cfg.file_to_read(), &Some("images/cat.png".to_owned()));
+            assert_eq!(cfg.output_file, "out/output.png".to_owned());
+            assert!(cfg.lsb);
+
+            let cfg = super::super::Config::from_args(
+                &args(&["decode", "images/harold.png", "-p", "password123!"]),
+                env.clone(),
+            );
+            assert_eq!(cfg.mode, super::super::Mode::Read);
+            assert_eq!(cfg.file_to_read(), &Some("images/harold.png".to_owned()));
+            assert_eq!(cfg.password, Some("password123!".to_string()));
+
+            let cfg = super::super::Config::from_args(
+                &args(&["get", "images/cat.png", "github", "--retry", "--reveal"]),
+                env.clone(),
+            );
+            assert_eq!(
+                cfg.vault,
+                Some(super::super::vault::Operation::Get("github".to_string()))
+            );
+            assert!(cfg.retry);
+            assert!(cfg.reveal);
+
+            let cfg = super::super::Config::from_args(
+                &args(&["inspect", "images/cat.png", "--lsb"]),
+                env.clone(),
+            );
+            assert!(cfg.info);
+            assert!(cfg.lsb);
+
+            let cfg = super::super::Config::from_args(
+                &args(&["strip", "images/cat.png", "--verify"]),
+                env,
+            );
+            assert!(cfg.strip);
+            assert!(cfg.verify);
+            assert_eq!(cfg.output_file, "result/cat.png".to_owned());
+        }
+
+        #[test]
+        fn reads_the_password_from_its_source() {
+            let path = std::env::temp_dir().join("impass_reads_the_password.txt");
+            std::fs::write(&path, "password123!\n").unwrap();
+            let source = format!("file:{}", path.to_str().unwrap());
+
+            let cfg = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "encode".to_string(),
+                    "images/cat.png".to_string(),
+                    "--pass-from".to_string(),
+                    source.clone(),
+                    "--secret-from".to_string(),
+                    "env:IMPASS_SECRET".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+
+            assert_eq!(cfg.password, Some("password123!".to_string()));
+            assert_eq!(
+                cfg.pass_from,
+                Some(super::super::utils::source::Source::File(
+                    path.to_str().unwrap().to_string()
+                ))
+            );
+            assert_eq!(
+                cfg.secret_from,
+                Some(super::super::utils::source::Source::Env(
+                    "IMPASS_SECRET".to_string()
+                ))
+            );
+        }
+
+        #[test]
+        #[should_panic(
+            expected = "Pass the password either with -p or with --pass-from, not both!"
+        )]
+        fn cant_pass_the_password_twice() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "decode".to_string(),
+                    "images/cat.png".to_string(),
+                    "-p".to_string(),
+                    "password123!".to_string(),
+                    "--pass-from".to_string(),
+                    "env:IMPASS_PASS".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Unrecognized option or flag -i for the encode command")]
+        fn commands_only_take_their_own_options() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "encode".to_string(),
+                    "images/cat.png".to_string(),
+                    "-i".to_string(),
+                    "images/harold.png".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Usage: impass add FILE NAME")]
+        fn commands_need_all_their_arguments() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "add".to_string(),
+                    "images/cat.png".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Unknown env action create, use: impass env generate")]
+        fn env_command_only_generates() {
+            let _ = super::super::Config::from_args(
+                &[
+                    "--".to_string(), // needed to distinguish the initial arg (program name),
+                    "env".to_string(),
+                    "create".to_string(),
+                ],
+                super::super::utils::env::collect_env(super::super::utils::Env::new()),
+            );
+        }
+
         #[test]
         #[should_panic(expected = "Unrecognized option or flag -asdkashdkajsdhkhk")]
         fn test_invalid_x_flag() {
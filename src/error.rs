@@ -0,0 +1,154 @@
+/// Everything that can go wrong while storing secrets into a file
+/// or getting them back out of it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImpassError {
+    /// The file is of none of the supported formats.
+    UnsupportedFormat,
+    /// The file claims to be of a supported `format`, but is not a valid one.
+    InvalidFile {
+        format: &'static str,
+        reason: String,
+    },
+    /// Secrets cannot be hidden within the samples of files of this `format`,
+    /// or not within this one for the given `reason`.
+    CannotHide {
+        format: &'static str,
+        reason: Option<String>,
+    },
+    /// The secrets are larger than the file can store, at most `limit` bytes when known.
+    TooLarge {
+        limit: Option<usize>,
+    },
+    /// The file is too small to hide the secrets within its samples.
+    TooSmall {
+        needed: usize,
+        capacity: usize,
+    },
+    NoSecrets,
+    AlreadyHoldsSecrets,
+    /// The file holds several named secrets, so none of them is the one to decode.
+    SeveralSecrets(Vec<String>),
+    Truncated,
+    CorruptedHeader,
+    /// The key derivation parameters stored within the payload are invalid.
+    InvalidKdf,
+    /// The secret of an image encoded before the container existed could not be decoded.
+    Damaged,
+    /// The payload uses a format version newer than this impass knows about.
+    UnsupportedVersion(u8),
+    UnknownCipher(u8),
+    PasswordRequired,
+    WrongPassword,
+    /// The password cannot be checked against images encoded before the container existed.
+    InvalidPassword(&'static str),
+    /// The file was encoded with other env parameters.
+    DifferentConfiguration,
+    /// Decrypting failed, with no password to blame it on.
+    DecryptionFailed,
+    /// The env variable of the given name does not hold a valid number.
+    InvalidEnv(&'static str),
+    Randomness,
+    KeyDerivation,
+}
+
+impl ImpassError {
+    /// The file is not a valid one of the given `format`, for the given `reason`.
+    pub fn invalid(format: &'static str, reason: impl Into<String>) -> Self {
+        Self::InvalidFile {
+            format,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ImpassError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedFormat => write!(
+                f,
+                "File is not a supported format, use one of: {}",
+                crate::carrier::supported()
+            ),
+            Self::InvalidFile { format, reason } => {
+                write!(f, "File is not a valid {format}: {reason}!")
+            }
+            Self::CannotHide {
+                format,
+                reason: None,
+            } => write!(
+                f,
+                "Secrets cannot be hidden within the contents of {format} files!"
+            ),
+            Self::CannotHide {
+                format,
+                reason: Some(reason),
+            } => write!(
+                f,
+                "Cannot hide secrets within this {format} file: {reason}"
+            ),
+            Self::TooLarge { limit: None } => {
+                write!(f, "Secret is too large to be stored in an image!")
+            }
+            Self::TooLarge { limit: Some(limit) } => write!(
+                f,
+                "Secret is too large to be stored in this file (at most {limit} bytes)!"
+            ),
+            Self::TooSmall { needed, capacity } => write!(
+                f,
+                "The file is too small to hide your secrets: they need {needed} bytes, but it only holds {capacity}."
+            ),
+            Self::NoSecrets => write!(f, "The file holds no secrets."),
+            Self::AlreadyHoldsSecrets => write!(f, "The file already holds secrets!"),
+            Self::SeveralSecrets(names) => write!(
+                f,
+                "The file holds several secrets ({}), pick one by name.",
+                names.join(", ")
+            ),
+            Self::Truncated => write!(f, "Payload is truncated, the image seems to be damaged!"),
+            Self::CorruptedHeader => write!(f, "Payload header is corrupted (checksum mismatch)!"),
+            Self::InvalidKdf => write!(
+                f,
+                "Invalid key derivation parameters, the image seems to be damaged!"
+            ),
+            Self::Damaged => write!(
+                f,
+                "Could not decrypt your secret, the image seems to be damaged!"
+            ),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Payload uses format version {version}, but this impass only supports up to version {}. Please update impass.",
+                crate::utils::constants::FORMAT_VERSION
+            ),
+            Self::UnknownCipher(cipher) => write!(
+                f,
+                "The file uses an unknown cipher ({cipher}). Please update impass."
+            ),
+            Self::PasswordRequired => write!(
+                f,
+                "The file is password-protected, a password is needed to decrypt it."
+            ),
+            Self::WrongPassword => write!(f, "Passwords do not match!"),
+            Self::InvalidPassword(reason) => write!(f, "{reason}"),
+            Self::DifferentConfiguration => write!(
+                f,
+                "The file was encoded with a different configuration (.env) than yours, so its secrets cannot be decrypted. Use the configuration it was encoded with."
+            ),
+            Self::DecryptionFailed => write!(
+                f,
+                "Could not decrypt your secrets: the password is wrong, the image was tampered with or it was encoded using a different configuration!"
+            ),
+            Self::InvalidEnv("MAX_ATTEMPTS") => write!(
+                f,
+                "Number provided for MAX_ATTEMPTS must be a whole number between 0 and 255!"
+            ),
+            Self::InvalidEnv(name) => write!(
+                f,
+                "Number provided for {name} must be a positive whole number!"
+            ),
+            Self::Randomness => write!(f, "Could not gather randomness from your system!"),
+            Self::KeyDerivation => write!(f, "Could not derive your encryption key!"),
+        }
+    }
+}
+
+impl std::error::Error for ImpassError {}
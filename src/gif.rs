@@ -0,0 +1,257 @@
+use crate::error::ImpassError;
+
+/// The signatures GIF files start with, one per version.
+pub static SIGNATURES: [[u8; 6]; 2] = [*b"GIF87a", *b"GIF89a"];
+
+/// The application identifier of the extension
+/// blocks impass payloads are stored in.
+pub static APPLICATION: &[u8; 8] = b"IMPASS  ";
+
+/// The authentication codes following the application identifier,
+/// telling whether the file was upgraded from version 87a to store it.
+const KEPT: &[u8; 3] = b"1.0";
+const UPGRADED: &[u8; 3] = b"87a";
+
+/// Introduces every extension block.
+const EXTENSION: u8 = 0x21;
+/// Labels application extension blocks.
+const APPLICATION_LABEL: u8 = 0xFF;
+/// Introduces every image.
+const IMAGE: u8 = 0x2C;
+/// Ends the file.
+const TRAILER: u8 = 0x3B;
+
+/// GIF images, storing payloads in an application extension block.
+pub struct Gif;
+
+impl crate::carrier::Carrier for Gif {
+    fn name(&self) -> &'static str {
+        "GIF"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        SIGNATURES
+            .iter()
+            .any(|signature| bytes.starts_with(signature))
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        let Some(block) = blocks(bytes)?
+            .into_iter()
+            .find(|block| is_payload(bytes, block))
+        else {
+            return Ok(None);
+        };
+
+        // The first sub-block holds the application identifier
+        Ok(Some(
+            block.data[1..]
+                .iter()
+                .flat_map(|range| &bytes[range.clone()])
+                .copied()
+                .collect(),
+        ))
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let trailer = self.end(bytes)?;
+
+        let mut embedded = bytes[..trailer].to_vec();
+        // Extension blocks were introduced with version 89a
+        let code = if bytes.starts_with(&SIGNATURES[0]) {
+            UPGRADED
+        } else {
+            KEPT
+        };
+        embedded[..6].copy_from_slice(&SIGNATURES[1]);
+
+        embedded.extend([EXTENSION, APPLICATION_LABEL, 11]);
+        embedded.extend(APPLICATION);
+        embedded.extend(code);
+        for data in payload.chunks(255) {
+            embedded.push(data.len() as u8);
+            embedded.extend(data);
+        }
+        embedded.push(0);
+
+        embedded.push(TRAILER);
+        Ok(embedded)
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let blocks = blocks(bytes)?;
+
+        let mut stripped = bytes[..blocks[0].start].to_vec();
+        for block in blocks {
+            if !is_payload(bytes, &block) {
+                stripped.extend(&bytes[block.start..block.end]);
+            } else if bytes[block.data[0].end - 3..block.data[0].end] == *UPGRADED {
+                stripped[..6].copy_from_slice(&SIGNATURES[0]);
+            }
+        }
+        Ok(stripped)
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        Ok(blocks(bytes)?.last().unwrap().start)
+    }
+}
+
+/// A single block of a GIF file, as positions within its bytes.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    /// Where the block (its introducer) starts.
+    pub start: usize,
+    /// The introducer of the block, followed by
+    /// the label of extension blocks.
+    pub kind: u8,
+    pub label: Option<u8>,
+    /// The data sub-blocks of the block, without their sizes.
+    pub data: Vec<std::ops::Range<usize>>,
+    pub end: usize,
+}
+
+fn is_payload(bytes: &[u8], block: &Block) -> bool {
+    block.label == Some(APPLICATION_LABEL)
+        && block
+            .data
+            .first()
+            .is_some_and(|range| range.len() == 11 && bytes[range.clone()].starts_with(APPLICATION))
+}
+
+/// Reads the data sub-blocks starting `at`, up to their terminator.
+fn sub_blocks(
+    bytes: &[u8],
+    mut at: usize,
+) -> Result<(Vec<std::ops::Range<usize>>, usize), ImpassError> {
+    let mut data = Vec::new();
+    loop {
+        if bytes.len() <= at {
+            return Err(ImpassError::invalid("GIF", "it is truncated"));
+        }
+
+        let len = bytes[at] as usize;
+        if len == 0 {
+            return Ok((data, at + 1));
+        }
+        if bytes.len() < at + 1 + len {
+            return Err(ImpassError::invalid("GIF", "it is truncated"));
+        }
+
+        data.push(at + 1..at + 1 + len);
+        at += 1 + len;
+    }
+}
+
+/// The size of the color table following a block with the `packed` fields.
+fn color_table(packed: u8) -> usize {
+    if packed & 0x80 != 0 {
+        3 << ((packed & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Parses the blocks of the GIF in `bytes`, after its
+/// header and global color table, up to and including its trailer.
+pub fn blocks(bytes: &[u8]) -> Result<Vec<Block>, ImpassError> {
+    if !SIGNATURES
+        .iter()
+        .any(|signature| bytes.starts_with(signature))
+    {
+        return Err(ImpassError::invalid("GIF", "it has no GIF signature"));
+    }
+    if bytes.len() < 13 {
+        return Err(ImpassError::invalid("GIF", "it is truncated"));
+    }
+
+    let mut blocks = Vec::new();
+    let mut at = 13 + color_table(bytes[10]);
+    loop {
+        if bytes.len() <= at {
+            return Err(ImpassError::invalid("GIF", "it is truncated"));
+        }
+
+        let kind = bytes[at];
+        let (label, data, end) = match kind {
+            EXTENSION if bytes.len() > at + 1 => {
+                let (data, end) = sub_blocks(bytes, at + 2)?;
+                (Some(bytes[at + 1]), data, end)
+            }
+            IMAGE if bytes.len() >= at + 11 => {
+                // Descriptor, local color table and the LZW minimum code size
+                let start = at + 10 + color_table(bytes[at + 9]) + 1;
+                let (data, end) = sub_blocks(bytes, start)?;
+                (None, data, end)
+            }
+            TRAILER => (None, Vec::new(), at + 1),
+            EXTENSION | IMAGE => return Err(ImpassError::invalid("GIF", "it is truncated")),
+            _ => {
+                return Err(ImpassError::invalid(
+                    "GIF",
+                    format!("unexpected data at byte {at}"),
+                ))
+            }
+        };
+
+        blocks.push(Block {
+            start: at,
+            kind,
+            label,
+            data,
+            end,
+        });
+
+        if kind == TRAILER {
+            return Ok(blocks);
+        }
+        at = end;
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::carrier::Carrier;
+
+    #[test]
+    fn parses_blocks() {
+        let bytes = std::fs::read("images/stripes.gif").unwrap();
+        let blocks = super::blocks(&bytes).unwrap();
+
+        assert_eq!(blocks[0].kind, super::IMAGE);
+        assert_eq!(blocks.last().unwrap().kind, super::TRAILER);
+        assert_eq!(blocks.last().unwrap().end, bytes.len());
+    }
+
+    #[test]
+    fn plain_images_have_no_payload() {
+        let bytes = std::fs::read("images/stripes.gif").unwrap();
+
+        assert_eq!(super::Gif.extract(&bytes), Ok(None));
+    }
+
+    #[test]
+    fn embeds_and_strips_payloads_over_several_sub_blocks() {
+        let bytes = std::fs::read("images/stripes.gif").unwrap();
+        let payload = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+
+        let embedded = super::Gif.embed(&bytes, &payload).unwrap();
+
+        assert_eq!(super::Gif.extract(&embedded), Ok(Some(payload)));
+        assert_eq!(&embedded[..6], b"GIF89a");
+        // Downgraded back to the very same 87a file
+        assert_eq!(super::Gif.strip(&embedded), Ok(bytes.clone()));
+        assert_eq!(
+            super::Gif.strip(&super::Gif.embed(&embedded, b"again").unwrap()),
+            Ok(bytes)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "File is not a valid GIF: it is truncated!")]
+    fn rejects_truncated_images() {
+        let bytes = std::fs::read("images/stripes.gif").unwrap();
+
+        crate::or_exit!(super::blocks(&bytes[..bytes.len() - 2]));
+    }
+}
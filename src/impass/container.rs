@@ -0,0 +1,260 @@
+use crate::error::ImpassError;
+use crate::utils::{self, constants};
+
+/// Flag set in the header when the payload is password-protected.
+pub const FLAG_HAS_PASS: u8 = 0b0000_0001;
+
+/// ChaCha20-Poly1305 over the whole entry table. Images encoded before
+/// the container existed carry no cipher id and use the legacy scheme.
+pub const CIPHER_CHACHA20_POLY1305: u8 = 1;
+
+/// Size of a serialized header:
+/// magic (4) + version (1) + flags (1) + body length (4) + checksum (4)
+pub const HEADER_SIZE: usize = 14;
+
+/// The self-describing header every impass payload starts with.
+/// It lets the decoder tell an impass image apart from a plain PNG
+/// and reject formats it does not know about.
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub flags: u8,
+    pub length: u32,
+}
+
+impl Header {
+    /// Creates a header of the current format version
+    /// for a body of the given `length`.
+    pub fn new(flags: u8, length: u32) -> Self {
+        Self {
+            version: constants::FORMAT_VERSION,
+            flags,
+            length,
+        }
+    }
+
+    /// Whether the payload is password-protected.
+    pub fn has_pass(&self) -> bool {
+        self.flags & FLAG_HAS_PASS != 0
+    }
+
+    /// Serializes the header, checksum included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend(constants::CONTAINER_MAGIC);
+        bytes.push(self.version);
+        bytes.push(self.flags);
+        bytes.extend(self.length.to_be_bytes());
+
+        let checksum = utils::crc32(&bytes);
+        bytes.extend(checksum.to_be_bytes());
+        bytes
+    }
+
+    /// Parses a header from the start of `bytes`.
+    /// Yields `None` if the bytes do not start with the impass magic,
+    /// which is the case for plain PNGs and images encoded before
+    /// the container existed.
+    pub fn parse(bytes: &[u8]) -> Result<Option<Self>, ImpassError> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != constants::CONTAINER_MAGIC {
+            return Ok(None);
+        }
+
+        let checksum = u32::from_be_bytes(bytes[10..14].try_into().unwrap());
+        if utils::crc32(&bytes[0..10]) != checksum {
+            return Err(ImpassError::CorruptedHeader);
+        }
+
+        let version = bytes[4];
+        if version == 0 || version > constants::FORMAT_VERSION {
+            return Err(ImpassError::UnsupportedVersion(version));
+        }
+
+        Ok(Some(Self {
+            version,
+            flags: bytes[5],
+            length: u32::from_be_bytes(bytes[6..10].try_into().unwrap()),
+        }))
+    }
+}
+
+/// The bytes authenticated alongside the encrypted body, so that neither
+/// the version, the flags nor the attempt limit can be tampered with.
+/// The failed attempts so far change with every wrong password, so
+/// they cannot be authenticated without it.
+pub fn associated_data(version: u8, flags: u8, max_attempts: u8) -> Vec<u8> {
+    let mut bytes = constants::CONTAINER_MAGIC.to_vec();
+    bytes.push(version);
+    bytes.push(flags);
+    bytes.push(max_attempts);
+    bytes
+}
+
+/// Wraps the given `body` into a container with a fresh header.
+pub fn wrap(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut bytes = Header::new(flags, body.len() as u32).to_bytes();
+    bytes.extend(body);
+    bytes
+}
+
+/// Opens the container at the start of `payload`, yielding its
+/// header and body, or `None` if there is no container there.
+pub fn open(payload: &[u8]) -> Result<Option<(Header, &[u8])>, ImpassError> {
+    let Some(header) = Header::parse(payload)? else {
+        return Ok(None);
+    };
+
+    let end = HEADER_SIZE + header.length as usize;
+    if payload.len() < end {
+        return Err(ImpassError::Truncated);
+    }
+
+    Ok(Some((header, &payload[HEADER_SIZE..end])))
+}
+
+/// Size of the length prefix of every field in the body of the
+/// given format `version`. Legacy images (version 0) only had
+/// single-byte lengths, capping fields at 255 bytes.
+pub fn length_size(version: u8) -> usize {
+    if version == 0 {
+        1
+    } else {
+        4
+    }
+}
+
+/// Appends a length-prefixed field to a body of the current version.
+pub fn write_field(body: &mut Vec<u8>, data: &[u8]) -> Result<(), ImpassError> {
+    let len = u32::try_from(data.len()).map_err(|_| ImpassError::TooLarge { limit: None })?;
+
+    body.extend(len.to_be_bytes());
+    body.extend(data);
+    Ok(())
+}
+
+/// Reads the length-prefixed field at `cursor` from a body of the given
+/// format `version`, moving the cursor past it.
+pub fn read_field<'a>(
+    body: &'a [u8],
+    cursor: &mut usize,
+    version: u8,
+) -> Result<&'a [u8], ImpassError> {
+    let size = length_size(version);
+    if body.len() < *cursor + size {
+        return Err(ImpassError::Truncated);
+    }
+
+    let len = body[*cursor..*cursor + size]
+        .iter()
+        .fold(0usize, |len, byte| (len << 8) | *byte as usize);
+    *cursor += size;
+
+    if body.len() < *cursor + len {
+        return Err(ImpassError::Truncated);
+    }
+
+    let field = &body[*cursor..*cursor + len];
+    *cursor += len;
+    Ok(field)
+}
+
+mod test {
+    #[test]
+    fn header_round_trips() {
+        let header = super::Header::new(super::FLAG_HAS_PASS, 1234);
+        let bytes = header.to_bytes();
+
+        assert_eq!(bytes.len(), super::HEADER_SIZE);
+        assert_eq!(super::Header::parse(&bytes), Ok(Some(header)));
+    }
+
+    #[test]
+    fn plain_data_is_not_a_container() {
+        assert_eq!(
+            super::Header::parse(&[1, 12, 42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Ok(None)
+        );
+        assert_eq!(super::Header::parse(b"IMPS"), Ok(None));
+    }
+
+    #[test]
+    fn wrap_and_open_works() {
+        let bytes = super::wrap(0, b"some body");
+        let (header, body) = super::open(&bytes).unwrap().unwrap();
+
+        assert!(!header.has_pass());
+        assert_eq!(body, b"some body");
+    }
+
+    #[test]
+    fn fields_round_trip() {
+        let long = vec![42u8; 70_000];
+        let mut body = vec![];
+        super::write_field(&mut body, b"short").unwrap();
+        super::write_field(&mut body, &long).unwrap();
+
+        let mut cursor = 0;
+        let version = crate::utils::constants::FORMAT_VERSION;
+        assert_eq!(
+            super::read_field(&body, &mut cursor, version),
+            Ok(&b"short"[..])
+        );
+        assert_eq!(
+            super::read_field(&body, &mut cursor, version),
+            Ok(&long[..])
+        );
+        assert_eq!(cursor, body.len());
+    }
+
+    #[test]
+    fn reads_single_byte_lengths_of_legacy_images() {
+        let body = [3, b'a', b'b', b'c', 1, b'd'];
+
+        let mut cursor = 0;
+        assert_eq!(super::read_field(&body, &mut cursor, 0), Ok(&b"abc"[..]));
+        assert_eq!(super::read_field(&body, &mut cursor, 0), Ok(&b"d"[..]));
+    }
+
+    #[test]
+    fn rejects_truncated_field() {
+        let mut body = vec![];
+        super::write_field(&mut body, b"some field").unwrap();
+
+        assert_eq!(
+            super::read_field(&body[..8], &mut 0, crate::utils::constants::FORMAT_VERSION),
+            Err(crate::error::ImpassError::Truncated)
+        );
+    }
+
+    #[test]
+    fn rejects_corrupted_header() {
+        let mut bytes = super::Header::new(0, 10).to_bytes();
+        bytes[6] ^= 0xFF;
+
+        assert_eq!(
+            super::Header::parse(&bytes),
+            Err(crate::error::ImpassError::CorruptedHeader)
+        );
+    }
+
+    #[test]
+    fn rejects_future_versions() {
+        let mut header = super::Header::new(0, 10);
+        header.version = 200;
+
+        assert_eq!(
+            super::Header::parse(&header.to_bytes()),
+            Err(crate::error::ImpassError::UnsupportedVersion(200))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_body() {
+        let bytes = super::wrap(0, b"some body");
+
+        assert_eq!(
+            super::open(&bytes[..bytes.len() - 2]),
+            Err(crate::error::ImpassError::Truncated)
+        );
+    }
+}
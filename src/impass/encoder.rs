@@ -1,118 +1,288 @@
-/// Encodes the given secret into an
-/// image (provided via config)
+use super::vault::Entry;
+use crate::error::ImpassError;
+
+/// Encodes the given `secret` into the image bytes in `content` as its
+/// default entry, and writes the result to the output file of the `config`.
+/// Errors if the image already holds secrets, unless asked to replace them.
+pub fn encode(config: &crate::config::Config, content: &mut Vec<u8>, secret: &[u8]) {
+    let options = config.options();
+    // Derived once, both to look for secrets and to hide the new ones
+    let order = crate::or_exit!(super::decoder::order_key(&options));
+
+    // A second payload would go unnoticed, as only the first one is ever found
+    let found = crate::or_exit!(super::decoder::find_with(
+        content,
+        &options,
+        order.as_deref()
+    ));
+    if let Some((_, location)) = found {
+        let file = config.file_to_read().clone().unwrap_or_default();
+        if !config.replace {
+            crate::error!(format!(
+                "The file {file} already holds secrets! Pass --replace to overwrite them, or --add NAME to store another one alongside them."
+            ));
+        }
+
+        crate::info!(format!(
+            "The file {file} already holds secrets, replacing them..."
+        ));
+        *content = crate::or_exit!(super::strip::remove(content, &location));
+    }
+
+    let entries = [Entry::new(super::vault::DEFAULT_ENTRY, secret)];
+    Encoder::new(config, content, &entries, order).encode();
+}
+
+/// Seals the given named entries into an image (provided
+/// via config), without writing it anywhere
 /// @param config - the Config for the current run
-/// @param secret - the data to be stored into the image
-/// ```rust
-/// encode(config, "Hello, World!", 23456);
-/// ```
-pub fn encode(config: &crate::config::Config, content: &mut Vec<u8>, secret: &String) {
-    Encoder::new(config, content, secret).encode();
+/// @param entries - the named secrets to be stored into the image
+/// @param order - the key from `decoder::order_key`, if already derived
+pub fn seal_entries(
+    config: &crate::config::Config,
+    content: &mut Vec<u8>,
+    entries: &[Entry],
+    order: Option<Vec<u8>>,
+) {
+    Encoder::new(config, content, entries, order).seal();
+}
+
+/// Seals the given named entries into the file in `content`, protected
+/// with the password, attempt limit and env parameters from the `options`,
+/// and hidden within its samples when they ask for it.
+pub fn seal(
+    content: &[u8],
+    entries: &[Entry],
+    options: &crate::Options,
+) -> Result<Vec<u8>, ImpassError> {
+    seal_with(
+        content,
+        entries,
+        options,
+        super::decoder::order_key(options)?.as_deref(),
+    )
+}
+
+/// Seals the given named entries like `seal`, hiding them within
+/// the samples given the `order` key from `decoder::order_key`.
+pub fn seal_with(
+    content: &[u8],
+    entries: &[Entry],
+    options: &crate::Options,
+    order: Option<&[u8]>,
+) -> Result<Vec<u8>, ImpassError> {
+    use crate::utils::crypt;
+
+    // The password is checked by decrypting with the key derived
+    // from it, no hash of it is stored within the image.
+    let mut flags = 0;
+    let password = options.password.as_deref().map(|pass| {
+        flags |= super::container::FLAG_HAS_PASS;
+        crate::utils::normalize_password(pass)
+    });
+
+    let kdf = crypt::KdfParams::default();
+    let salt = crypt::random_bytes(crypt::SALT_SIZE)?;
+    let nonce = crypt::random_bytes(crypt::NONCE_SIZE)?;
+    let key = crypt::derive_key(password.as_deref(), &salt, &kdf, &options.env)?;
+    let aad = super::container::associated_data(
+        crate::utils::constants::FORMAT_VERSION,
+        flags,
+        options.max_attempts,
+    );
+    let sealed = crypt::encrypt_payload(&super::vault::serialize(entries)?, &aad, &key, &nonce)?;
+
+    let mut body = vec![super::container::CIPHER_CHACHA20_POLY1305];
+    // The attempt limit, and no failed attempts so far
+    body.push(options.max_attempts);
+    body.push(0);
+    super::container::write_field(&mut body, &crypt::env_fingerprint(&salt, &options.env)?)?;
+    super::container::write_field(&mut body, &kdf.to_bytes())?;
+    super::container::write_field(&mut body, &salt)?;
+    super::container::write_field(&mut body, &nonce)?;
+    super::container::write_field(&mut body, &sealed)?;
+
+    let payload = super::container::wrap(flags, &body);
+    let carrier = crate::carrier::of(content)?;
+    match order {
+        Some(order) => carrier.hide(content, &payload, order),
+        None => carrier.embed(content, &payload),
+    }
 }
 
 #[derive(Debug)]
 struct Encoder<'a> {
     config: &'a crate::config::Config,
     content: &'a mut Vec<u8>,
-    secret: &'a String,
+    entries: &'a [Entry],
+    /// The key the samples are shuffled with, if already derived.
+    order: Option<Vec<u8>>,
 }
 
 impl<'a> Encoder<'a> {
     pub fn new(
         config: &'a crate::config::Config,
         content: &'a mut Vec<u8>,
-        secret: &'a String,
+        entries: &'a [Entry],
+        order: Option<Vec<u8>>,
     ) -> Self {
         Self {
             config,
             content,
-            secret,
+            entries,
+            order,
         }
     }
 
-    fn use_pass(&mut self, pass: &String) {
-        crate::info!("Incorporating password...");
-        self.content.push(1);
-
-        use super::super::utils;
-
-        let hashed = utils::impassible_hash(pass);
-        crate::info!("Encrypting your passsword..");
-        if let Ok(res) = bcrypt::hash(hashed.to_string(), utils::constants::BCRYPT_COST) {
-            let len = res.len();
-            self.content.push(len as u8);
-            self.content.extend(res.chars().map(|x| x as u8));
-        } else {
-            crate::unreachable!("BCrypt failed for some reason");
-        }
+    fn encode(&mut self) {
+        self.seal();
+        save_file(self.config, self.content)
     }
 
-    fn encode(&mut self) {
-        if let Some(pass) = &self.config.password {
-            self.use_pass(pass);
-        } else {
-            // NO_HAS_PASS
-            self.content.push(0);
+    fn seal(&mut self) {
+        if self.config.password.is_some() {
+            crate::info!("Incorporating password...");
         }
-
-        let secret: String = self.secret.chars().rev().collect();
-
-        let crypt = crate::utils::crypt::encrypt_secret(&secret, &self.config.env);
-
-        if &crate::utils::crypt::decrypt_secret(&crypt, &self.config.env) != self.secret {
-            crate::error!("Your config seems to be incorrect. Please change its values or pass --generate-env once ro regenerate it and try running the program again");
+        if self.config.lsb {
+            crate::info!("Hiding your secrets within the samples of the file...");
         }
 
-        self.content.push(crypt.len() as u8);
-
-        // TODO: not a bad idea to be able to store the config env
-        // within the content?
+        let options = self.config.options();
+        if self.order.is_none() {
+            self.order = crate::or_exit!(super::decoder::order_key(&options));
+        }
 
-        self.content.extend(crypt);
-        self.save_file()
+        *self.content = crate::or_exit!(seal_with(
+            self.content,
+            self.entries,
+            &options,
+            self.order.as_deref()
+        ));
     }
+}
 
-    fn save_file(&self) {
-        if std::fs::write(&self.config.output_file, &self.content).is_err() {
-            if std::fs::create_dir(&self.config.output_file).is_ok() {
-                std::fs::write(&self.config.output_file, &self.content).unwrap();
-            } else {
-                crate::error!(format!("Could not create your output file \"{}\". Does the program have the right permissions?", &self.config.output_file));
-            }
+/// Writes the `content` into the output file of the `config`.
+pub fn save_file(config: &crate::config::Config, content: &[u8]) {
+    if std::fs::write(&config.output_file, content).is_err() {
+        if std::fs::create_dir(&config.output_file).is_ok() {
+            std::fs::write(&config.output_file, content).unwrap();
+        } else {
+            crate::error!(format!("Could not create your output file \"{}\". Does the program have the right permissions?", &config.output_file));
         }
-        println!("Done :)");
     }
+    eprintln!("Done :)");
 }
 
-// TODO: tests
 mod test {
     #[allow(unused_imports)]
-    use super::super::test::{generate_config, get_content};
+    use super::super::test::{decode_file, encode_file, file_config, generate_config, get_content};
 
     #[test]
     fn encode_without_password() {
-        let encoding_config = super::super::test::generate_config(
+        let cfg = file_config(
+            "images/harold.png",
+            None,
+            "impass_encode_without_password.png",
+        );
+
+        let encoded = encode_file(&cfg, b"encoding!");
+
+        assert_eq!(std::fs::read(&cfg.output_file).unwrap(), encoded);
+        assert_eq!(
+            decode_file(&cfg.output_file, None),
+            Some(b"encoding!".to_vec())
+        );
+    }
+
+    #[test]
+    fn encode_with_password() {
+        let cfg = file_config(
+            "images/harold.png",
+            Some("pass123"),
+            "impass_encode_with_password.png",
+        );
+
+        let encoded = encode_file(&cfg, b"encoding!");
+
+        assert_eq!(std::fs::read(&cfg.output_file).unwrap(), encoded);
+        assert_eq!(decode_file(&cfg.output_file, Some("wrong")), None);
+        assert_eq!(
+            decode_file(&cfg.output_file, Some("pass123")),
+            Some(b"encoding!".to_vec())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "already holds secrets! Pass --replace to overwrite them")]
+    fn refuses_to_encode_over_secrets() {
+        let config = generate_config(
             None,
             crate::config::Mode::Read,
-            "images/harold.png".to_string(),
+            "images/gradient.bmp".to_string(),
         );
 
         let mut bytes = vec![];
-        let _ = get_content(&encoding_config, &mut bytes);
+        let _ = get_content(&config, &mut bytes);
 
-        super::encode(&encoding_config, &mut bytes, &"encoding!".to_string());
+        super::encode(&config, &mut bytes, b"first!");
+        super::encode(&config, &mut bytes, b"second!");
     }
 
     #[test]
-    fn encode_with_password() {
-        let encoding_config = super::super::test::generate_config(
+    fn replaces_secrets_when_asked_to() {
+        let mut config = generate_config(
             Some("pass123".to_string()),
             crate::config::Mode::Read,
-            "images/harold.png".to_string(),
+            "images/gradient.png".to_string(),
         );
+        config.replace = true;
 
         let mut bytes = vec![];
-        let _ = get_content(&encoding_config, &mut bytes);
+        let _ = get_content(&config, &mut bytes);
+
+        super::encode(&config, &mut bytes, b"first!");
+        super::encode(&config, &mut bytes, b"second!");
+
+        let payloads = crate::png::chunks(&bytes)
+            .unwrap()
+            .iter()
+            .filter(|chunk| chunk.kind == crate::png::PAYLOAD_CHUNK)
+            .count();
+        assert_eq!(payloads, 1);
+        assert_eq!(
+            crate::impass::decoder::decode(&config, &mut bytes, false),
+            Some(b"second!".to_vec())
+        );
+    }
+
+    #[test]
+    fn replaces_secrets_hidden_within_pixels() {
+        let mut config = generate_config(
+            Some("pass123".to_string()),
+            crate::config::Mode::Read,
+            "images/gradient.png".to_string(),
+        );
+        config.lsb = true;
+        config.replace = true;
+
+        let mut bytes = vec![];
+        let _ = get_content(&config, &mut bytes);
+        super::encode(&config, &mut bytes, b"first!");
+        super::encode(&config, &mut bytes, b"second!");
+
+        config.replace = false;
+        assert!(crate::impass::decoder::holds_secrets(&config, &bytes));
+        assert_eq!(
+            crate::impass::decoder::decode(&config, &mut bytes, false),
+            Some(b"second!".to_vec())
+        );
+        assert_eq!(
+            crate::carrier::of(&bytes).unwrap().extract(&bytes),
+            Ok(None)
+        );
 
-        super::encode(&encoding_config, &mut bytes, &"encoding!".to_string());
+        // Only looked for when asked to
+        config.lsb = false;
+        assert!(!crate::impass::decoder::holds_secrets(&config, &bytes));
     }
 }
@@ -0,0 +1,245 @@
+use crate::error::ImpassError;
+use crate::utils::crypt::KdfParams;
+
+/// Everything that can be told about a file without decrypting it.
+#[derive(Debug, PartialEq)]
+pub struct Report {
+    /// The name of the format of the file.
+    pub format: &'static str,
+    /// Whether payloads can be hidden within the samples of the file,
+    /// where they cannot be found without their password.
+    pub can_hide: bool,
+    pub payload: Option<Metadata>,
+}
+
+/// The non-secret metadata of an impass payload.
+#[derive(Debug, PartialEq)]
+pub struct Metadata {
+    /// The size of the whole payload, header included.
+    pub size: usize,
+    /// Whether the payload was hidden within the samples (such as pixels) of the file.
+    pub lsb: bool,
+    /// The format version, 0 for images encoded before the container existed.
+    pub version: u8,
+    pub has_pass: bool,
+    /// The cipher id, unknown to legacy images.
+    pub cipher: Option<u8>,
+    /// The attempt limit and failed attempts so far, unknown to legacy images.
+    pub attempts: Option<(u8, u8)>,
+    /// The Argon2id parameters, unknown to legacy images.
+    pub kdf: Option<KdfParams>,
+    /// Whether the payload was encoded with the env parameters
+    /// from the config, unknown to legacy images.
+    pub env_matches: Option<bool>,
+}
+
+/// Inspects the file in `content` without decrypting anything. Payloads
+/// hidden within the samples can only be found with the password
+/// from the `options`, if any.
+pub fn inspect(content: &[u8], options: &crate::Options) -> Result<Report, ImpassError> {
+    let carrier = crate::carrier::of(content)?;
+    let payload = match super::decoder::find(content, options)? {
+        Some((payload, location)) => {
+            let lsb = matches!(location, crate::carrier::Location::Hidden(_));
+            Some(metadata(&payload, lsb, &options.env)?)
+        }
+        None => None,
+    };
+
+    Ok(Report {
+        format: carrier.name(),
+        can_hide: carrier.can_hide(content),
+        payload,
+    })
+}
+
+/// Reads the metadata preceding the encrypted part of the `payload`,
+/// parsed the same way the decoder does.
+fn metadata(payload: &[u8], lsb: bool, env: &crate::utils::Env) -> Result<Metadata, ImpassError> {
+    let parsed = super::payload::Payload::parse(payload)?;
+
+    Ok(Metadata {
+        size: payload.len(),
+        lsb,
+        version: parsed.version(),
+        has_pass: parsed.has_pass(),
+        cipher: parsed.cipher,
+        attempts: parsed.attempts(),
+        kdf: parsed.kdf.clone(),
+        env_matches: parsed.env_matches(env)?,
+    })
+}
+
+/// Prints the report on the file (provided via config).
+pub fn print(config: &crate::config::Config, content: &[u8]) {
+    let report = crate::or_exit!(inspect(content, &config.options()));
+    let file = config.file_to_read().clone().unwrap_or_default();
+
+    println!("File: {file}");
+    println!("Format: {}", report.format);
+
+    let Some(payload) = report.payload else {
+        println!("Payload: none");
+        if report.can_hide {
+            println!("Secrets hidden within the samples of the file with a password cannot be found without it.");
+        }
+        return;
+    };
+
+    let location = if payload.lsb {
+        "hidden within the samples of the file"
+    } else {
+        "stored within the file"
+    };
+    println!("Payload: {} bytes, {location}", payload.size);
+    println!("Format version: {}", payload.version);
+    println!(
+        "Password-protected: {}",
+        if payload.has_pass { "yes" } else { "no" }
+    );
+
+    let cipher = match payload.cipher {
+        None => "legacy".to_string(),
+        Some(super::container::CIPHER_CHACHA20_POLY1305) => "ChaCha20-Poly1305".to_string(),
+        Some(cipher) => format!("unknown ({cipher})"),
+    };
+    println!("Cipher: {cipher}");
+
+    if let Some(kdf) = payload.kdf {
+        println!(
+            "Key derivation: Argon2id, {} KiB of memory, {} iteration(s), {} lane(s)",
+            kdf.memory, kdf.iterations, kdf.parallelism
+        );
+    }
+
+    match payload.env_matches {
+        Some(true) => println!("Configuration: matches yours"),
+        Some(false) => println!("Configuration: differs from yours, it cannot be decrypted"),
+        None => println!("Configuration: unknown, not recorded by legacy images"),
+    }
+
+    match payload.attempts {
+        Some((0, _)) | None => println!("Attempts: unlimited"),
+        Some((max_attempts, failed)) => {
+            println!("Attempts: {failed} failed out of {max_attempts} allowed")
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::impass::test::file_config;
+
+    #[test]
+    fn reports_plain_files() {
+        let cfg = file_config("images/gradient.bmp", None, "impass_inspect");
+        let content = std::fs::read("images/gradient.bmp").unwrap();
+
+        let report = super::inspect(&content, &cfg.options()).unwrap();
+
+        assert_eq!(report.format, "BMP");
+        assert!(!report.can_hide);
+        assert_eq!(report.payload, None);
+    }
+
+    #[test]
+    fn reports_legacy_payloads() {
+        let cfg = file_config("tests/asdasd_pass.png", None, "impass_inspect");
+        let content = std::fs::read("tests/asdasd_pass.png").unwrap();
+
+        let payload = super::inspect(&content, &cfg.options())
+            .unwrap()
+            .payload
+            .unwrap();
+
+        assert_eq!(payload.version, 0);
+        assert!(payload.has_pass);
+        assert_eq!(payload.cipher, None);
+        assert_eq!(payload.attempts, None);
+    }
+
+    #[test]
+    fn reports_current_payloads_without_a_password() {
+        let mut cfg = file_config("images/gradient.png", Some("pass123"), "impass_inspect");
+        cfg.max_attempts = Some(3);
+        let mut content = std::fs::read("images/gradient.png").unwrap();
+        crate::impass::encoder::encode(&cfg, &mut content, b"inspected!");
+
+        // Nothing gets decrypted, so no password is needed
+        let payload = super::inspect(
+            &content,
+            &file_config("images/gradient.png", None, "impass_inspect").options(),
+        )
+        .unwrap()
+        .payload
+        .unwrap();
+
+        assert_eq!(
+            payload.size,
+            crate::carrier::of(&content)
+                .unwrap()
+                .extract(&content)
+                .unwrap()
+                .unwrap()
+                .len()
+        );
+        assert!(!payload.lsb);
+        assert_eq!(payload.version, crate::utils::constants::FORMAT_VERSION);
+        assert!(payload.has_pass);
+        assert_eq!(
+            payload.cipher,
+            Some(crate::impass::container::CIPHER_CHACHA20_POLY1305)
+        );
+        assert_eq!(payload.attempts, Some((3, 0)));
+        assert_eq!(payload.kdf, Some(crate::utils::crypt::KdfParams::default()));
+        assert_eq!(payload.env_matches, Some(true));
+
+        // Nor does it touch the secrets themselves
+        let entries = crate::impass::decoder::open(&content, &cfg.options()).unwrap();
+        assert_eq!(entries[0].secret, b"inspected!");
+    }
+
+    #[test]
+    fn finds_hidden_payloads_only_with_their_password() {
+        let mut cfg = file_config("audio/beep.wav", Some("pass123"), "impass_inspect");
+        cfg.lsb = true;
+        let mut content = std::fs::read("audio/beep.wav").unwrap();
+        crate::impass::encoder::encode(&cfg, &mut content, b"inspected!");
+
+        let report = super::inspect(
+            &content,
+            &file_config("audio/beep.wav", None, "impass_inspect").options(),
+        )
+        .unwrap();
+        assert!(report.can_hide);
+        assert_eq!(report.payload, None);
+
+        let payload = super::inspect(&content, &cfg.options())
+            .unwrap()
+            .payload
+            .unwrap();
+        assert!(payload.lsb);
+        assert!(payload.has_pass);
+
+        let entries = crate::impass::decoder::open(&content, &cfg.options()).unwrap();
+        assert_eq!(entries[0].secret, b"inspected!");
+    }
+
+    #[test]
+    fn tells_payloads_of_other_configurations_apart() {
+        let cfg = file_config("images/gradient.bmp", None, "impass_inspect");
+        let mut content = std::fs::read("images/gradient.bmp").unwrap();
+        crate::impass::encoder::encode(&cfg, &mut content, b"inspected!");
+
+        let cfg = cfg.with_env(crate::utils::env::collect_env(crate::utils::Env::from([(
+            "SHIFT".to_string(),
+            "12".to_string(),
+        )])));
+        let payload = super::inspect(&content, &cfg.options())
+            .unwrap()
+            .payload
+            .unwrap();
+
+        assert_eq!(payload.env_matches, Some(false));
+    }
+}
@@ -1,5 +1,11 @@
+pub mod container;
 pub mod decoder;
 pub mod encoder;
+pub mod inspect;
+pub mod payload;
+pub mod rotate;
+pub mod strip;
+pub mod vault;
 
 pub(crate) mod test {
     // Stop Clippy from complaining
@@ -22,4 +28,62 @@ pub(crate) mod test {
     pub fn get_content(config: &crate::config::Config, content: &mut Vec<u8>) -> usize {
         crate::reading::gimme_bytecode(config, content)
     }
+
+    /// A config for the file at `path`, protected with the `password` (if
+    /// any) and written into `output` within the temporary directory.
+    #[allow(dead_code)]
+    pub fn file_config(
+        path: &str,
+        password: Option<&str>,
+        output: impl AsRef<std::path::Path>,
+    ) -> crate::config::Config {
+        crate::config::Config::new(
+            crate::config::Mode::File,
+            path.to_string(),
+            password.map(str::to_string),
+            std::env::temp_dir()
+                .join(output)
+                .to_str()
+                .unwrap()
+                .to_string(),
+            crate::utils::env::collect_env(crate::utils::Env::new()),
+        )
+    }
+
+    /// Encodes the `secret` into the file of the `config`, yielding
+    /// the encoded bytes as they were written into its output file.
+    #[allow(dead_code)]
+    pub fn encode_file(config: &crate::config::Config, secret: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let _ = get_content(config, &mut bytes);
+        crate::impass::encoder::encode(config, &mut bytes, secret);
+        bytes
+    }
+
+    /// Decodes the secret of the file of the `config`, yielding
+    /// `None` instead of panicking when that fails.
+    #[allow(dead_code)]
+    pub fn try_decode(config: &crate::config::Config) -> Option<Vec<u8>> {
+        let mut bytes = vec![];
+        let _ = get_content(config, &mut bytes);
+
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crate::impass::decoder::decode(config, &mut bytes, false)
+        }))
+        .ok()
+        .flatten()
+    }
+
+    /// Decodes the secret of the file at `path` with the `password` (if any),
+    /// looking within its samples as well. See `try_decode`.
+    #[allow(dead_code)]
+    pub fn decode_file(
+        path: impl AsRef<std::path::Path>,
+        password: Option<&str>,
+    ) -> Option<Vec<u8>> {
+        let path = path.as_ref().to_str().unwrap();
+        let mut config = file_config(path, password, path);
+        config.lsb = true;
+        try_decode(&config)
+    }
 }
@@ -0,0 +1,201 @@
+use super::vault::Entry;
+use crate::error::ImpassError;
+use crate::utils::crypt::{self, KdfParams};
+
+/// An impass payload, parsed up to its encrypted part.
+#[derive(Debug)]
+pub struct Payload<'a> {
+    pub header: super::container::Header,
+    /// The cipher id, unknown to images encoded before the container existed.
+    pub cipher: Option<u8>,
+    /// Where the attempt limit sits within the payload, followed by the
+    /// number of failed attempts so far.
+    pub guard: Option<usize>,
+    /// The Argon2id parameters the key was derived with.
+    pub kdf: Option<KdfParams>,
+    bytes: &'a [u8],
+    sealed: Sealed<'a>,
+}
+
+/// The encrypted part of a payload.
+#[derive(Debug)]
+enum Sealed<'a> {
+    /// Images encoded before the container existed: the bcrypt hash of
+    /// the password, if any, and a single anonymous secret.
+    Legacy {
+        crypt: Option<&'a [u8]>,
+        secret: &'a [u8],
+    },
+    /// The whole entry table, encrypted with ChaCha20-Poly1305.
+    Table {
+        fingerprint: &'a [u8],
+        salt: &'a [u8],
+        nonce: &'a [u8],
+        table: &'a [u8],
+    },
+}
+
+impl<'a> Payload<'a> {
+    /// Parses the payload in `bytes`, either within a container
+    /// or stored the way images were before the container existed.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ImpassError> {
+        let Some((header, body)) = super::container::open(bytes)? else {
+            return Self::parse_legacy(bytes);
+        };
+        let version = header.version;
+        let mut cursor = 0;
+
+        let Some(cipher) = body.get(cursor) else {
+            return Err(ImpassError::Truncated);
+        };
+        if *cipher != super::container::CIPHER_CHACHA20_POLY1305 {
+            return Err(ImpassError::UnknownCipher(*cipher));
+        }
+        cursor += 1;
+
+        // The attempt limit and the number of failed attempts so far
+        if body.len() < cursor + 2 {
+            return Err(ImpassError::Truncated);
+        }
+        let guard = super::container::HEADER_SIZE + cursor;
+        cursor += 2;
+
+        let fingerprint = super::container::read_field(body, &mut cursor, version)?;
+        let kdf = super::container::read_field(body, &mut cursor, version)?;
+        let kdf = KdfParams::from_bytes(kdf)?;
+
+        Ok(Self {
+            header,
+            cipher: Some(*cipher),
+            guard: Some(guard),
+            kdf: Some(kdf),
+            bytes,
+            sealed: Sealed::Table {
+                fingerprint,
+                salt: super::container::read_field(body, &mut cursor, version)?,
+                nonce: super::container::read_field(body, &mut cursor, version)?,
+                table: super::container::read_field(body, &mut cursor, version)?,
+            },
+        })
+    }
+
+    /// Images encoded before the container existed start with a single
+    /// has-pass byte, followed by the bcrypt hash of the password (if any)
+    /// and the secret, with single-byte lengths.
+    fn parse_legacy(bytes: &'a [u8]) -> Result<Self, ImpassError> {
+        let Some((flags, body)) = bytes.split_first() else {
+            return Err(ImpassError::Truncated);
+        };
+        let header = super::container::Header {
+            version: 0,
+            flags: *flags,
+            length: body.len() as u32,
+        };
+
+        let mut cursor = 0;
+        let crypt = if header.has_pass() {
+            Some(super::container::read_field(body, &mut cursor, 0)?)
+        } else {
+            None
+        };
+        let secret = super::container::read_field(body, &mut cursor, 0)?;
+
+        Ok(Self {
+            header,
+            cipher: None,
+            guard: None,
+            kdf: None,
+            bytes,
+            sealed: Sealed::Legacy { crypt, secret },
+        })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.header.version
+    }
+
+    pub fn has_pass(&self) -> bool {
+        self.header.has_pass()
+    }
+
+    /// The attempt limit and failed attempts so far, unknown to legacy images.
+    pub fn attempts(&self) -> Option<(u8, u8)> {
+        self.guard.map(|at| (self.bytes[at], self.bytes[at + 1]))
+    }
+
+    /// Whether the payload was encoded with the given env parameters,
+    /// unknown to legacy images.
+    pub fn env_matches(&self, env: &crate::utils::Env) -> Result<Option<bool>, ImpassError> {
+        let Sealed::Table {
+            fingerprint, salt, ..
+        } = &self.sealed
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(*fingerprint == crypt::env_fingerprint(salt, env)?))
+    }
+
+    /// Errors if the payload was encoded with other env parameters. Checked
+    /// before asking for a password, so that it does not count as a failed attempt.
+    pub fn check_env(&self, env: &crate::utils::Env) -> Result<(), ImpassError> {
+        if self.env_matches(env)? == Some(false) {
+            return Err(ImpassError::DifferentConfiguration);
+        }
+        Ok(())
+    }
+
+    /// Decrypts the entries of the payload with the `password`, which
+    /// password-protected payloads cannot do without.
+    pub fn unlock(
+        &self,
+        password: Option<&str>,
+        env: &crate::utils::Env,
+    ) -> Result<Vec<Entry>, ImpassError> {
+        if self.has_pass() && password.is_none() {
+            return Err(ImpassError::PasswordRequired);
+        }
+
+        let (salt, nonce, table) = match &self.sealed {
+            Sealed::Legacy { crypt, secret } => {
+                if let Some(crypt) = crypt {
+                    let crypt = crypt.iter().map(|c| *c as char).collect::<String>();
+                    let hashed =
+                        crate::utils::impassible_hash(&password.unwrap_or_default().to_string())?;
+
+                    if !bcrypt::verify(hashed.to_string(), &crypt)
+                        .map_err(|_| ImpassError::Damaged)?
+                    {
+                        return Err(ImpassError::WrongPassword);
+                    }
+                }
+
+                let secret = crypt::decrypt_secret(secret, env)?;
+                return Ok(vec![Entry::new(super::vault::DEFAULT_ENTRY, &secret)]);
+            }
+            Sealed::Table {
+                salt, nonce, table, ..
+            } => (salt, nonce, table),
+        };
+
+        self.check_env(env)?;
+
+        let Some(kdf) = &self.kdf else {
+            return Err(ImpassError::InvalidKdf);
+        };
+        let password = password.map(crate::utils::normalize_password);
+        let key = crypt::derive_key(password.as_deref(), salt, kdf, env)?;
+
+        let Some((max_attempts, _)) = self.attempts() else {
+            return Err(ImpassError::Truncated);
+        };
+        let aad =
+            super::container::associated_data(self.version(), self.header.flags, max_attempts);
+        match crypt::try_decrypt_payload(table, &aad, &key, nonce)? {
+            Some(table) => super::vault::deserialize(&table),
+            // A wrong password only shows by failing to decrypt
+            None if self.has_pass() => Err(ImpassError::WrongPassword),
+            None => Err(ImpassError::DecryptionFailed),
+        }
+    }
+}
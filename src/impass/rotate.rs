@@ -0,0 +1,472 @@
+use super::payload::Payload;
+use super::vault::Entry;
+use crate::error::ImpassError;
+use std::path::{Path, PathBuf};
+
+/// What a key rotation did with every file it came across.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub rotated: Vec<PathBuf>,
+    /// The files left as they were, alongside the reason why.
+    pub skipped: Vec<(PathBuf, String)>,
+    /// The files which could not be rotated, alongside the reason why.
+    /// No file gets written when any of them fails.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl Report {
+    pub fn print(&self) {
+        for path in &self.rotated {
+            println!("Rotated: {}", path.display());
+        }
+        for (path, reason) in &self.skipped {
+            println!("Skipped: {} ({reason})", path.display());
+        }
+        for (path, reason) in &self.failed {
+            println!("Failed: {} ({reason})", path.display());
+        }
+        if !self.failed.is_empty() {
+            println!("No file was rotated, every file was left as it was.");
+        }
+        println!(
+            "{} file(s) rotated, {} skipped, {} failed.",
+            self.rotated.len(),
+            self.skipped.len(),
+            self.failed.len()
+        );
+    }
+}
+
+/// Why a file was not rotated.
+enum Outcome {
+    /// It holds no secrets to rotate.
+    Skipped(String),
+    /// It holds secrets which could not be rotated.
+    Failed(String),
+}
+
+/// A file whose secrets were re-encrypted, yet to be written.
+struct Rotation {
+    path: PathBuf,
+    original: Vec<u8>,
+    rotated: Vec<u8>,
+}
+
+/// Re-encrypts the secrets of every file within the directory to rotate
+/// (provided via config, along with the env files) under the new env
+/// parameters. Every file gets unlocked, re-encrypted and verified before
+/// any of them is written, and the written ones are rolled back should
+/// writing any other fail. Unlocking never records failed attempts, so
+/// a wrong password leaves every file as it was.
+pub fn rotate(config: &crate::config::Config) -> Report {
+    let (Some(dir), Some(from)) = (&config.rotate, &config.rotate_from) else {
+        crate::unreachable!("Directory or env to rotate not provided");
+    };
+    let old_env = crate::utils::env::read_env_file(from);
+    let new_env = config
+        .rotate_to
+        .as_deref()
+        .map_or_else(|| config.env.clone(), crate::utils::env::read_env_file);
+
+    let mut paths = Vec::new();
+    walk(Path::new(dir), &mut paths);
+    paths.sort();
+
+    let mut report = Report::default();
+    let mut rotations = Vec::new();
+    for path in paths {
+        match reencrypt(config, &path, &old_env, &new_env) {
+            Ok(rotation) => rotations.push(rotation),
+            Err(Outcome::Skipped(reason)) => report.skipped.push((path, reason)),
+            Err(Outcome::Failed(reason)) => report.failed.push((path, reason)),
+        }
+    }
+    if !report.failed.is_empty() {
+        return report;
+    }
+
+    let mut written: Vec<&Rotation> = Vec::new();
+    for rotation in &rotations {
+        if let Err(err) = write_atomically(&rotation.path, &rotation.rotated) {
+            report.failed.push((
+                rotation.path.clone(),
+                format!("could not be written: {err}"),
+            ));
+            for done in written.iter().rev() {
+                if let Err(err) = write_atomically(&done.path, &done.original) {
+                    report.failed.push((
+                        done.path.clone(),
+                        format!("rotated, but could not be rolled back: {err}"),
+                    ));
+                }
+            }
+            return report;
+        }
+        written.push(rotation);
+    }
+
+    report.rotated = rotations
+        .into_iter()
+        .map(|rotation| rotation.path)
+        .collect();
+    report
+}
+
+/// Collects every file within `dir` and its subdirectories. Symbolic
+/// links are collected as they are, never followed, so that neither
+/// loops nor directories outside of `dir` get walked into.
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        crate::error!(format!(
+            "Could not read the directory \"{}\"!",
+            dir.display()
+        ));
+    };
+
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            walk(&entry.path(), paths);
+        } else {
+            paths.push(entry.path());
+        }
+    }
+}
+
+/// Unlocks the secrets of the file at `path` with the `old_env` and
+/// re-encrypts them with the `new_env`, the same way they were stored.
+/// Yields why the file was skipped, if it holds no secrets, or why it failed.
+fn reencrypt(
+    config: &crate::config::Config,
+    path: &Path,
+    old_env: &crate::utils::Env,
+    new_env: &crate::utils::Env,
+) -> Result<Rotation, Outcome> {
+    let file = path.display().to_string();
+    let failed = |err: ImpassError| Outcome::Failed(err.to_string());
+    // Writing would replace the link, leaving whatever it links to as it was
+    if path.symlink_metadata().is_ok_and(|meta| meta.is_symlink()) {
+        return Err(Outcome::Skipped(
+            "a symbolic link, not followed".to_string(),
+        ));
+    }
+    let content =
+        std::fs::read(path).map_err(|err| Outcome::Failed(format!("could not be read: {err}")))?;
+    let Some(carrier) = crate::carrier::detect(&content) else {
+        return Err(Outcome::Skipped("not a supported format".to_string()));
+    };
+
+    let mut old = config.options();
+    old.env = old_env.clone();
+    let Some((found, location)) = super::decoder::find(&content, &old).map_err(failed)? else {
+        if !carrier.can_hide(&content) {
+            return Err(Outcome::Skipped("holds no secrets".to_string()));
+        }
+
+        // Secrets hidden within the samples may have been missed, and could
+        // no longer be decoded under the new env, so only skip when asked to
+        let reason = if !config.lsb {
+            "holds no secrets, or hides them within its samples: pass --lsb"
+        } else if config.password.is_none() {
+            "holds no secrets, or hides them with a password: pass it with -p"
+        } else {
+            "holds no secrets, or hides them with another password"
+        };
+        return Err(if config.skip_plain {
+            Outcome::Skipped(reason.to_string())
+        } else {
+            Outcome::Failed(format!("{reason}, or --skip-plain if it holds none"))
+        });
+    };
+
+    crate::info!(format!("Rotating {file}..."));
+    let payload = Payload::parse(&found).map_err(failed)?;
+    let (entries, password) = unlock(config, &file, &payload, old_env).map_err(failed)?;
+
+    // Overwrite secrets hidden within the samples, as
+    // the new ones may be hidden within other ones
+    let stripped = super::strip::remove(&content, &location).map_err(failed)?;
+    let new = crate::Options {
+        password,
+        env: new_env.clone(),
+        max_attempts: payload
+            .attempts()
+            .map_or(0, |(max_attempts, _)| max_attempts),
+        lsb: matches!(location, crate::carrier::Location::Hidden(_)),
+        replace: false,
+    };
+    // Derived once, both to hide the secrets and to verify them
+    let order = super::decoder::order_key(&new).map_err(failed)?;
+    let rotated =
+        super::encoder::seal_with(&stripped, &entries, &new, order.as_deref()).map_err(failed)?;
+
+    if super::decoder::open_with(&rotated, &new, order.as_deref()).ok() != Some(entries) {
+        return Err(Outcome::Failed(
+            "could not verify the rotated secrets".to_string(),
+        ));
+    }
+
+    Ok(Rotation {
+        path: path.to_path_buf(),
+        original: content,
+        rotated,
+    })
+}
+
+/// Unlocks the `payload` of the `file` with the password from the config,
+/// asking for it otherwise. Wrong passwords are never recorded within the
+/// file, nor do they destroy it.
+fn unlock(
+    config: &crate::config::Config,
+    file: &str,
+    payload: &Payload,
+    env: &crate::utils::Env,
+) -> Result<(Vec<Entry>, Option<String>), ImpassError> {
+    if !payload.has_pass() {
+        return Ok((payload.unlock(None, env)?, None));
+    }
+    // Checked first, so that the password is not asked for in vain
+    payload.check_env(env)?;
+
+    loop {
+        let pass = config.password.clone().unwrap_or_else(|| {
+            crate::utils::prompt_password(&format!("Please, enter the password of {file}"))
+        });
+
+        match payload.unlock(Some(&pass), env) {
+            Ok(entries) => return Ok((entries, Some(pass))),
+            Err(ImpassError::WrongPassword) if config.retry && config.password.is_none() => {
+                crate::info!("Passwords do not match!");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Writes `bytes` into a temporary file next to `path`, then moves it
+/// over `path`, so that the file is never left half written.
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{name}.impass-tmp"));
+
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary);
+    })
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::impass::test::{file_config, try_decode};
+    #[allow(unused_imports)]
+    use std::path::{Path, PathBuf};
+
+    /// Fills a fresh directory with an encoded PNG destroyed by a single wrong
+    /// password, WAV hiding its secrets within its samples, plain BMP and
+    /// a file of an unsupported format.
+    #[allow(dead_code)]
+    fn vault_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("audio")).unwrap();
+
+        for (source, target, lsb) in [
+            ("images/gradient.png", "gradient.png", false),
+            ("audio/beep.wav", "audio/beep.wav", true),
+        ] {
+            let mut cfg = file_config(source, Some("pass123"), dir.join(target));
+            cfg.lsb = lsb;
+            cfg.max_attempts = Some(1);
+
+            let mut content = std::fs::read(source).unwrap();
+            crate::impass::encoder::encode(&cfg, &mut content, b"rotate me!");
+        }
+
+        std::fs::copy("images/gradient.bmp", dir.join("gradient.bmp")).unwrap();
+        std::fs::copy("README.md", dir.join("README.md")).unwrap();
+        std::fs::write(dir.join("old.env"), "SHIFT=11\nGODNUM=42\nXOR=69\n").unwrap();
+        std::fs::write(dir.join("new.env"), "SHIFT=12\nGODNUM=7\nXOR=99\n").unwrap();
+        dir
+    }
+
+    #[allow(dead_code)]
+    fn rotate_config(dir: &Path, password: &str) -> crate::config::Config {
+        let path = |file: &str| Some(dir.join(file).to_str().unwrap().to_string());
+
+        let mut cfg = file_config(dir.to_str().unwrap(), Some(password), dir);
+        cfg.rotate = path("");
+        cfg.rotate_from = path("old.env");
+        cfg.rotate_to = path("new.env");
+        cfg.lsb = true;
+        cfg.skip_plain = true;
+        cfg
+    }
+
+    /// Decodes the file at `path` with the env parameters of the `env` file.
+    #[allow(dead_code)]
+    fn decode(path: &Path, env: &Path) -> Option<Vec<u8>> {
+        let mut cfg = file_config(path.to_str().unwrap(), Some("pass123"), path)
+            .with_env(crate::utils::env::read_env_file(env.to_str().unwrap()));
+        cfg.lsb = true;
+        try_decode(&cfg)
+    }
+
+    #[test]
+    fn rotates_every_file_holding_secrets() {
+        let dir = vault_dir("impass_rotates_every_file");
+
+        let report = super::rotate(&rotate_config(&dir, "pass123"));
+
+        assert_eq!(
+            report.rotated,
+            vec![dir.join("audio/beep.wav"), dir.join("gradient.png")]
+        );
+        let skipped = report
+            .skipped
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(skipped, ["README.md", "gradient.bmp", "new.env", "old.env"]);
+        assert!(report.failed.is_empty());
+
+        // Still destroyed by a single wrong password
+        let rotated = std::fs::read(dir.join("gradient.png")).unwrap();
+        let payload = crate::carrier::of(&rotated)
+            .unwrap()
+            .extract(&rotated)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            crate::impass::payload::Payload::parse(&payload)
+                .unwrap()
+                .attempts(),
+            Some((1, 0))
+        );
+
+        for file in ["audio/beep.wav", "gradient.png"] {
+            let path = dir.join(file);
+            assert_eq!(
+                decode(&path, &dir.join("new.env")),
+                Some(b"rotate me!".to_vec())
+            );
+            assert_eq!(decode(&path, &dir.join("old.env")), None);
+        }
+        assert_eq!(
+            std::fs::read(dir.join("gradient.bmp")).unwrap(),
+            std::fs::read("images/gradient.bmp").unwrap()
+        );
+    }
+
+    #[test]
+    fn wrong_passwords_leave_every_file_as_it_was() {
+        let dir = vault_dir("impass_rotation_writes_nothing");
+        let files = ["audio/beep.wav", "gradient.bmp", "gradient.png"];
+        let before = files.map(|file| std::fs::read(dir.join(file)).unwrap());
+
+        // The WAV cannot be found, and the PNG cannot be unlocked
+        let report = super::rotate(&rotate_config(&dir, "wrong"));
+
+        assert!(report.rotated.is_empty());
+        assert_eq!(
+            report.failed,
+            vec![(
+                dir.join("gradient.png"),
+                "Passwords do not match!".to_string()
+            )]
+        );
+        for (file, before) in files.iter().zip(before) {
+            assert_eq!(std::fs::read(dir.join(file)).unwrap(), before);
+        }
+
+        // Nor did the wrong password count as a failed attempt
+        let report = super::rotate(&rotate_config(&dir, "pass123"));
+        assert_eq!(
+            report.rotated,
+            vec![dir.join("audio/beep.wav"), dir.join("gradient.png")]
+        );
+        for file in ["audio/beep.wav", "gradient.png"] {
+            assert_eq!(
+                decode(&dir.join(file), &dir.join("new.env")),
+                Some(b"rotate me!".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn files_which_may_hide_secrets_fail_unless_skipped() {
+        let dir = vault_dir("impass_rotation_may_miss_secrets");
+        let files = ["audio/beep.wav", "gradient.bmp", "gradient.png"];
+        let before = files.map(|file| std::fs::read(dir.join(file)).unwrap());
+
+        // Without --lsb, the secrets hidden within the WAV cannot be found
+        let mut cfg = rotate_config(&dir, "pass123");
+        cfg.lsb = false;
+        cfg.skip_plain = false;
+        let report = super::rotate(&cfg);
+
+        // While BMP files cannot hide secrets to begin with
+        assert!(report.rotated.is_empty());
+        assert_eq!(
+            report.failed,
+            [(
+                dir.join("audio/beep.wav"),
+                "holds no secrets, or hides them within its samples: pass --lsb, or --skip-plain if it holds none".to_string()
+            )]
+        );
+        for (file, before) in files.iter().zip(before) {
+            assert_eq!(std::fs::read(dir.join(file)).unwrap(), before);
+        }
+
+        cfg.skip_plain = true;
+        let report = super::rotate(&cfg);
+        assert_eq!(report.rotated, [dir.join("gradient.png")]);
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            decode(&dir.join("gradient.png"), &dir.join("new.env")),
+            Some(b"rotate me!".to_vec())
+        );
+        // Left hidden with the old parameters
+        assert_eq!(
+            decode(&dir.join("audio/beep.wav"), &dir.join("old.env")),
+            Some(b"rotate me!".to_vec())
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn never_follows_symbolic_links() {
+        let dir = vault_dir("impass_rotation_follows_no_links");
+        let outside = vault_dir("impass_rotation_outside_links");
+        std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("outside")).unwrap();
+        std::os::unix::fs::symlink(dir.join("gradient.png"), dir.join("link.png")).unwrap();
+        let before = std::fs::read(outside.join("gradient.png")).unwrap();
+
+        let report = super::rotate(&rotate_config(&dir, "pass123"));
+
+        assert_eq!(
+            report.rotated,
+            vec![dir.join("audio/beep.wav"), dir.join("gradient.png")]
+        );
+        for link in ["link.png", "loop", "outside"] {
+            assert!(report
+                .skipped
+                .contains(&(dir.join(link), "a symbolic link, not followed".to_string())));
+        }
+        assert!(std::fs::symlink_metadata(dir.join("link.png"))
+            .unwrap()
+            .is_symlink());
+        assert_eq!(std::fs::read(outside.join("gradient.png")).unwrap(), before);
+    }
+
+    #[test]
+    fn writes_files_atomically() {
+        let dir = std::env::temp_dir().join("impass_writes_atomically");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        super::write_atomically(&dir.join("file"), b"contents").unwrap();
+
+        assert_eq!(std::fs::read(dir.join("file")).unwrap(), b"contents");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+}
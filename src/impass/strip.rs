@@ -0,0 +1,140 @@
+use crate::carrier::Location;
+use crate::error::ImpassError;
+
+/// Removes the secrets from an image (provided via config), writing
+/// the file it was before being encoded into the output file.
+/// Secrets hidden within the samples (such as pixels) of the file
+/// replaced the original ones, so those get overwritten with noise.
+/// @param config - the Config for the current run, unlocking the
+/// secrets first when asked to verify their password
+pub fn strip(config: &crate::config::Config, content: &mut Vec<u8>) {
+    let location = super::decoder::locate(config, content, config.verify);
+    match location {
+        Location::Embedded => crate::info!("Stripping your secrets..."),
+        Location::Hidden(_) => crate::info!("Your secrets are hidden within the samples of the file, which cannot be restored as they were. Overwriting them with noise..."),
+    }
+
+    *content = crate::or_exit!(remove(content, &location));
+    super::encoder::save_file(config, content);
+}
+
+/// Removes the payload found at the given `location` from the `content`,
+/// overwriting payloads hidden within its samples with noise.
+pub fn remove(content: &[u8], location: &Location) -> Result<Vec<u8>, ImpassError> {
+    let carrier = crate::carrier::of(content)?;
+
+    match location {
+        Location::Embedded => carrier.strip(content),
+        Location::Hidden(key) => {
+            let (_, hidden) = carrier.reveal(content, &[key]).unwrap_or_default();
+            let noise = crate::utils::crypt::random_bytes(hidden.len())?;
+            carrier.hide(content, &noise, key)
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::impass::test::file_config;
+
+    #[test]
+    fn restores_every_format_byte_for_byte() {
+        for (image, output) in [
+            ("images/gradient.png", "impass_strip.png"),
+            ("images/kekw.jpg", "impass_strip.jpg"),
+            ("images/pixel.webp", "impass_strip.webp"),
+            ("images/stripes.gif", "impass_strip.gif"),
+            ("images/gradient.bmp", "impass_strip.bmp"),
+            ("audio/beep.wav", "impass_strip.wav"),
+        ] {
+            let cfg = file_config(image, Some("pass123"), output);
+            let original = std::fs::read(image).unwrap();
+
+            let mut content = original.clone();
+            crate::impass::encoder::encode(&cfg, &mut content, b"stripped!");
+            assert_ne!(content, original);
+
+            super::strip(&cfg, &mut content);
+
+            assert_eq!(content, original, "{image} was not restored");
+            assert_eq!(std::fs::read(&cfg.output_file).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn restores_images_with_legacy_payloads() {
+        let cfg = file_config("tests/asdasd_pass.png", None, "impass_strip_legacy.png");
+        let original = std::fs::read("tests/asdasd_pass.png").unwrap();
+        let mut content = original.clone();
+
+        super::strip(&cfg, &mut content);
+
+        // Nothing trails the IEND chunk anymore
+        let end = crate::png::chunks(&original).unwrap().last().unwrap().end();
+        assert!(end < original.len());
+        assert_eq!(content, &original[..end]);
+        assert_eq!(std::fs::read(&cfg.output_file).unwrap(), content);
+    }
+
+    #[test]
+    fn overwrites_secrets_hidden_within_pixels() {
+        let mut cfg = file_config(
+            "images/gradient.png",
+            Some("pass123"),
+            "impass_strip_lsb.png",
+        );
+        cfg.lsb = true;
+        cfg.verify = true;
+
+        let original = std::fs::read("images/gradient.png").unwrap();
+        let mut content = original.clone();
+        crate::impass::encoder::encode(&cfg, &mut content, b"stripped!");
+        let encoded = content.clone();
+        assert!(crate::impass::decoder::holds_secrets(&cfg, &content));
+
+        super::strip(&cfg, &mut content);
+
+        assert!(!crate::impass::decoder::holds_secrets(&cfg, &content));
+        assert_ne!(content, encoded);
+        assert_eq!(std::fs::read(&cfg.output_file).unwrap(), content);
+        // Only the image data was rewritten, every other chunk is left as-is
+        let others = |bytes: &[u8]| {
+            crate::png::chunks(bytes)
+                .unwrap()
+                .iter()
+                .filter(|chunk| chunk.kind != *b"IDAT")
+                .map(|chunk| bytes[chunk.start..chunk.end()].to_vec())
+                .collect::<Vec<Vec<u8>>>()
+        };
+        assert_eq!(others(&content), others(&original));
+    }
+
+    #[test]
+    #[should_panic(expected = "Passwords do not match!")]
+    fn verifies_the_password_first() {
+        let cfg = file_config(
+            "images/gradient.bmp",
+            Some("pass123"),
+            "impass_strip_verify.bmp",
+        );
+        let mut content = std::fs::read("images/gradient.bmp").unwrap();
+        crate::impass::encoder::encode(&cfg, &mut content, b"stripped!");
+
+        let mut cfg = file_config(
+            "images/gradient.bmp",
+            Some("wrong"),
+            "impass_strip_verify.bmp",
+        );
+        cfg.verify = true;
+        super::strip(&cfg, &mut content);
+    }
+
+    #[test]
+    #[should_panic(expected = "holds no secrets")]
+    fn refuses_plain_files() {
+        let cfg = file_config("images/gradient.bmp", None, "impass_strip_plain.bmp");
+        let mut content = std::fs::read("images/gradient.bmp").unwrap();
+
+        super::strip(&cfg, &mut content);
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::ImpassError;
+
+/// Name given to the secret of images encoded without picking an
+/// entry name, including every image encoded before vaults existed.
+pub const DEFAULT_ENTRY: &str = "default";
+
+/// A single named secret stored within an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub secret: Vec<u8>,
+}
+
+impl Entry {
+    pub fn new(name: &str, secret: &[u8]) -> Self {
+        Self {
+            name: name.to_string(),
+            secret: secret.to_vec(),
+        }
+    }
+}
+
+/// Serializes the given entries into an entry table:
+/// a 32-bit count followed by the name and secret of every entry.
+pub fn serialize(entries: &[Entry]) -> Result<Vec<u8>, ImpassError> {
+    let mut table = (entries.len() as u32).to_be_bytes().to_vec();
+    for entry in entries {
+        super::container::write_field(&mut table, entry.name.as_bytes())?;
+        super::container::write_field(&mut table, &entry.secret)?;
+    }
+    Ok(table)
+}
+
+/// Reads the entry count at `cursor` of an entry table,
+/// moving the cursor past it.
+fn read_count(table: &[u8], cursor: &mut usize) -> Result<u32, ImpassError> {
+    if table.len() < *cursor + 4 {
+        return Err(ImpassError::Truncated);
+    }
+
+    let count = u32::from_be_bytes(table[*cursor..*cursor + 4].try_into().unwrap());
+    *cursor += 4;
+    Ok(count)
+}
+
+/// Deserializes an entry table produced by `serialize`.
+pub fn deserialize(table: &[u8]) -> Result<Vec<Entry>, ImpassError> {
+    let version = crate::utils::constants::FORMAT_VERSION;
+    let mut cursor = 0;
+    let count = read_count(table, &mut cursor)?;
+
+    (0..count)
+        .map(|_| {
+            let name = super::container::read_field(table, &mut cursor, version)?;
+            let secret = super::container::read_field(table, &mut cursor, version)?;
+
+            Ok(Entry {
+                name: String::from_utf8_lossy(name).to_string(),
+                secret: secret.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// An operation over the named entries stored within an image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add(String),
+    List,
+    Get(String),
+    Update(String),
+    Remove(String),
+}
+
+/// Loads the entries stored within the image, alongside the password
+/// they were unlocked with. Plain images yield an empty vault, and so
+/// do files hiding secrets within their samples with another password.
+/// Those would be overwritten when `adding` a secret hidden there,
+/// so that errors instead, unless the user asked to replace them.
+fn load(
+    config: &crate::config::Config,
+    content: &mut Vec<u8>,
+    adding: bool,
+) -> super::decoder::Contents {
+    let options = config.options();
+    // Derived once, both to look for the secrets and to hide them again
+    let order = crate::or_exit!(super::decoder::order_key(&options));
+
+    let found = crate::or_exit!(super::decoder::find_with(
+        content,
+        &options,
+        order.as_deref()
+    ));
+    let Some(found) = found else {
+        let carrier = crate::or_exit!(crate::carrier::of(content));
+        if adding && order.is_some() && !config.replace && carrier.can_hide(content) {
+            crate::error!(format!(
+                "No secrets were found within the samples of {}, yet they may be hidden there with another password, which adding one would overwrite. Pass their password, or --replace to add it anyway.",
+                config.file_to_read().clone().unwrap_or_default()
+            ));
+        }
+
+        return super::decoder::Contents {
+            entries: Vec::new(),
+            password: config.password.clone(),
+            max_attempts: config.attempts_limit(),
+            order,
+        };
+    };
+
+    super::decoder::decode_found(config, content, found)
+}
+
+/// Replaces the payload of the image with the given `entries`, protecting
+/// it with the same password and attempt limit it was unlocked with,
+/// and hiding it the same way it was hidden.
+fn store(
+    config: &crate::config::Config,
+    content: &mut Vec<u8>,
+    entries: &[Entry],
+    unlocked: super::decoder::Contents,
+) {
+    reseal(config, content, entries, unlocked);
+    super::encoder::save_file(config, content);
+}
+
+/// Replaces the payload of the image like `store`, without writing it anywhere.
+pub fn reseal(
+    config: &crate::config::Config,
+    content: &mut Vec<u8>,
+    entries: &[Entry],
+    unlocked: super::decoder::Contents,
+) {
+    let carrier = crate::or_exit!(crate::carrier::of(content));
+    *content = crate::or_exit!(carrier.strip(content));
+
+    let mut config = config.clone();
+    config.lsb |= unlocked.order.is_some();
+    // Hidden with the same key again, unless a password gets added
+    let mut order = unlocked.order;
+    if let Some(pass) = unlocked.password {
+        config.set_password(pass);
+    } else if config.password.is_some() {
+        order = None;
+    }
+    if config.max_attempts.is_none() {
+        config.max_attempts = Some(unlocked.max_attempts);
+    }
+
+    super::encoder::seal_entries(&config, content, entries, order);
+}
+
+fn position(entries: &[Entry], name: &str) -> Option<usize> {
+    entries.iter().position(|entry| entry.name == name)
+}
+
+/// Adds a new entry to the image. Errors if the name is taken.
+pub fn add(config: &crate::config::Config, content: &mut Vec<u8>, name: &str, secret: &[u8]) {
+    if name.is_empty() {
+        crate::error!("Entry name cannot be empty!");
+    }
+
+    let mut unlocked = load(config, content, true);
+    let mut entries = std::mem::take(&mut unlocked.entries);
+    if position(&entries, name).is_some() {
+        crate::error!(format!(
+            "Entry \"{name}\" already exists. Use --update to change it."
+        ));
+    }
+
+    if !entries.is_empty() {
+        crate::info!(format!(
+            "Adding \"{name}\" alongside the {} secret(s) the file already holds.",
+            entries.len()
+        ));
+    }
+    entries.push(Entry::new(name, secret));
+    store(config, content, &entries, unlocked);
+}
+
+/// Lists the names of all entries stored within the image.
+pub fn list(config: &crate::config::Config, content: &mut Vec<u8>) -> Vec<String> {
+    let entries = load(config, content, false).entries;
+
+    entries.into_iter().map(|entry| entry.name).collect()
+}
+
+/// Gets the secret of the entry with the given `name`.
+pub fn get(config: &crate::config::Config, content: &mut Vec<u8>, name: &str) -> Vec<u8> {
+    let entries = load(config, content, false).entries;
+
+    if let Some(idx) = position(&entries, name) {
+        entries[idx].secret.clone()
+    } else {
+        crate::error!(format!("No entry named \"{name}\" in this image."));
+    }
+}
+
+/// Replaces the secret of an existing entry, leaving the others as-is.
+pub fn update(config: &crate::config::Config, content: &mut Vec<u8>, name: &str, secret: &[u8]) {
+    let mut unlocked = load(config, content, false);
+    let mut entries = std::mem::take(&mut unlocked.entries);
+
+    if let Some(idx) = position(&entries, name) {
+        entries[idx].secret = secret.to_vec();
+    } else {
+        crate::error!(format!("No entry named \"{name}\" in this image."));
+    }
+
+    store(config, content, &entries, unlocked);
+}
+
+/// Removes an entry from the image, leaving the others as-is.
+pub fn remove(config: &crate::config::Config, content: &mut Vec<u8>, name: &str) {
+    let mut unlocked = load(config, content, false);
+    let mut entries = std::mem::take(&mut unlocked.entries);
+
+    if let Some(idx) = position(&entries, name) {
+        entries.remove(idx);
+    } else {
+        crate::error!(format!("No entry named \"{name}\" in this image."));
+    }
+
+    store(config, content, &entries, unlocked);
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::impass::test::{file_config, get_content};
+
+    #[test]
+    fn manages_entries_without_touching_others() {
+        let cfg = file_config("images/cat.png", None, "impass_vault_entries.png");
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+
+        super::add(&cfg, &mut bytes, "github", b"gh-token");
+        super::add(&cfg, &mut bytes, "aws-prod", b"aws-key");
+        super::add(&cfg, &mut bytes, "db-root", b"hunter2");
+        assert_eq!(
+            super::list(&cfg, &mut bytes),
+            vec!["github", "aws-prod", "db-root"]
+        );
+        assert_eq!(super::get(&cfg, &mut bytes, "github"), b"gh-token");
+
+        super::update(&cfg, &mut bytes, "aws-prod", b"new-aws-key");
+        super::remove(&cfg, &mut bytes, "github");
+
+        // Read back what was written to the output image
+        let cfg = file_config(&cfg.output_file, None, "impass_vault_entries.png");
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+
+        assert_eq!(super::list(&cfg, &mut bytes), vec!["aws-prod", "db-root"]);
+        assert_eq!(super::get(&cfg, &mut bytes, "aws-prod"), b"new-aws-key");
+        assert_eq!(super::get(&cfg, &mut bytes, "db-root"), b"hunter2");
+    }
+
+    #[test]
+    fn only_adds_over_secrets_hidden_with_another_password_when_replacing() {
+        let mut cfg = file_config(
+            "images/gradient.png",
+            Some("pass123"),
+            "impass_vault_hidden.png",
+        );
+        cfg.lsb = true;
+        crate::impass::test::encode_file(&cfg, b"hidden!");
+
+        let mut cfg = file_config(&cfg.output_file, Some("other"), "impass_vault_hidden.png");
+        cfg.lsb = true;
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+        let hidden = bytes.clone();
+
+        let error = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            super::add(&cfg, &mut bytes, "github", b"gh-token")
+        }))
+        .unwrap_err();
+        assert!(error
+            .downcast_ref::<String>()
+            .unwrap()
+            .contains("Pass their password, or --replace to add it anyway."));
+        assert_eq!(bytes, hidden);
+
+        cfg.replace = true;
+        super::add(&cfg, &mut bytes, "github", b"gh-token");
+        assert_eq!(super::list(&cfg, &mut bytes), vec!["github"]);
+        assert_eq!(super::get(&cfg, &mut bytes, "github"), b"gh-token");
+    }
+
+    #[test]
+    fn entry_tables_round_trip() {
+        let entries = vec![
+            super::Entry::new("github", b"gh-token"),
+            super::Entry::new("aws-prod", "🔑 ключ".as_bytes()),
+            super::Entry::new("binary", &[0, 159, 146, 150, 255]),
+        ];
+
+        let table = super::serialize(&entries).unwrap();
+
+        assert_eq!(super::deserialize(&table), Ok(entries));
+    }
+
+    #[test]
+    fn legacy_images_hold_a_default_entry() {
+        let cfg = file_config("tests/nopass.png", None, "impass_vault_legacy.png");
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+
+        assert_eq!(super::list(&cfg, &mut bytes), vec![super::DEFAULT_ENTRY]);
+        assert_eq!(
+            super::get(&cfg, &mut bytes, super::DEFAULT_ENTRY),
+            b"hello world!"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Entry \"github\" already exists. Use --update to change it.")]
+    fn cant_add_an_existing_entry() {
+        let cfg = file_config("images/cat.png", None, "impass_vault_existing.png");
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+
+        super::add(&cfg, &mut bytes, "github", b"gh-token");
+        super::add(&cfg, &mut bytes, "github", b"other-token");
+    }
+
+    #[test]
+    #[should_panic(expected = "No entry named \"nope\" in this image.")]
+    fn cant_get_a_missing_entry() {
+        let cfg = file_config("images/cat.png", None, "impass_vault_missing.png");
+        let mut bytes = vec![];
+        let _ = get_content(&cfg, &mut bytes);
+
+        super::add(&cfg, &mut bytes, "github", b"gh-token");
+        let _ = super::get(&cfg, &mut bytes, "nope");
+    }
+}
@@ -0,0 +1,313 @@
+use crate::error::ImpassError;
+
+/// The start of image (SOI) marker every JPEG file starts with,
+/// followed by the first byte of its next marker.
+pub static SIGNATURE: [u8; 3] = [0xFF, 0xD8, 0xFF];
+
+/// The segment impass payloads are stored in: APP15,
+/// which no common application makes use of.
+pub static PAYLOAD_MARKER: u8 = 0xEF;
+
+/// Identifies impass payload segments among other APP15 segments.
+pub static PAYLOAD_ID: &[u8; 7] = b"impass\0";
+
+/// The most payload a single segment holds, past its length and
+/// identifier. Larger payloads are spread over consecutive segments.
+pub const SEGMENT_PAYLOAD_SIZE: usize = u16::MAX as usize - 2 - PAYLOAD_ID.len();
+
+/// The start of scan marker, after which entropy-coded data follows.
+const SOS: u8 = 0xDA;
+/// The end of image marker.
+const EOI: u8 = 0xD9;
+
+/// JPEG images, storing payloads in a segment of their own.
+pub struct Jpeg;
+
+impl crate::carrier::Carrier for Jpeg {
+    fn name(&self) -> &'static str {
+        "JPEG"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&SIGNATURE)
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        extract(bytes)
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        embed(bytes, payload)
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        strip(bytes)
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        end(bytes)
+    }
+}
+
+/// A single segment of a JPEG file, as positions within its bytes.
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    /// Where the segment (its marker) starts.
+    pub start: usize,
+    pub marker: u8,
+    /// The data of the segment, excluding its length field.
+    pub data: std::ops::Range<usize>,
+    /// Where the segment ends, including any entropy-coded data after it.
+    pub end: usize,
+}
+
+/// Parses the segments of the JPEG in `bytes`, up to and including EOI.
+/// Entropy-coded data is skipped, so that bytes looking like an EOI marker
+/// within it (or within other segments) are not mistaken for the end.
+pub fn segments(bytes: &[u8]) -> Result<Vec<Segment>, ImpassError> {
+    if bytes.len() < SIGNATURE.len() || bytes[..SIGNATURE.len()] != SIGNATURE {
+        return Err(ImpassError::invalid("JPEG", "it has no JPEG signature"));
+    }
+
+    let mut segments = Vec::new();
+    let mut at = 2;
+    loop {
+        if bytes.len() < at + 2 {
+            return Err(ImpassError::invalid("JPEG", "it is truncated"));
+        }
+        if bytes[at] != 0xFF {
+            return Err(ImpassError::invalid(
+                "JPEG",
+                format!("unexpected data at byte {at}"),
+            ));
+        }
+
+        // Markers may be preceded by any number of fill bytes
+        let marker = bytes[at + 1];
+        if marker == 0xFF {
+            at += 1;
+            continue;
+        }
+
+        // Markers standing on their own, without a length
+        if marker == EOI || marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            segments.push(Segment {
+                start: at,
+                marker,
+                data: at + 2..at + 2,
+                end: at + 2,
+            });
+
+            if marker == EOI {
+                return Ok(segments);
+            }
+            at += 2;
+            continue;
+        }
+
+        if bytes.len() < at + 4 {
+            return Err(ImpassError::invalid("JPEG", "it is truncated"));
+        }
+        let len = u16::from_be_bytes([bytes[at + 2], bytes[at + 3]]) as usize;
+        if len < 2 || bytes.len() < at + 2 + len {
+            return Err(ImpassError::invalid("JPEG", "it is truncated"));
+        }
+
+        let data = at + 4..at + 2 + len;
+        let end = if marker == SOS {
+            scan_end(bytes, data.end)?
+        } else {
+            data.end
+        };
+
+        segments.push(Segment {
+            start: at,
+            marker,
+            data,
+            end,
+        });
+        at = end;
+    }
+}
+
+/// Finds where the entropy-coded data starting `at` ends: at the first
+/// marker which is neither a stuffed 0xFF byte nor a restart marker.
+fn scan_end(bytes: &[u8], mut at: usize) -> Result<usize, ImpassError> {
+    while at + 1 < bytes.len() {
+        if bytes[at] == 0xFF {
+            let next = bytes[at + 1];
+            if next != 0x00 && next != 0xFF && !(0xD0..=0xD7).contains(&next) {
+                return Ok(at);
+            }
+        }
+        at += 1;
+    }
+
+    Err(ImpassError::invalid("JPEG", "it is truncated"))
+}
+
+/// Serializes a segment with the given `marker` holding `data`.
+pub fn segment(marker: u8, data: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    if data.len() + 2 > u16::MAX as usize {
+        return Err(ImpassError::TooLarge {
+            limit: Some(u16::MAX as usize - 2),
+        });
+    }
+
+    let mut bytes = vec![0xFF, marker];
+    bytes.extend((data.len() as u16 + 2).to_be_bytes());
+    bytes.extend(data);
+    Ok(bytes)
+}
+
+fn is_payload(bytes: &[u8], segment: &Segment) -> bool {
+    segment.marker == PAYLOAD_MARKER && bytes[segment.data.clone()].starts_with(PAYLOAD_ID)
+}
+
+/// Extracts the impass payload of the JPEG in `bytes`: the data
+/// of every payload segment past its identifier, joined together.
+pub fn extract(bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+    let mut payload: Option<Vec<u8>> = None;
+    for segment in segments(bytes)? {
+        if is_payload(bytes, &segment) {
+            payload
+                .get_or_insert_with(Vec::new)
+                .extend(&bytes[segment.data.start + PAYLOAD_ID.len()..segment.data.end]);
+        }
+    }
+    Ok(payload)
+}
+
+/// The position of the EOI marker of the JPEG in `bytes`.
+pub fn end(bytes: &[u8]) -> Result<usize, ImpassError> {
+    Ok(segments(bytes)?.last().unwrap().start)
+}
+
+/// Stores the `payload` in as many consecutive payload segments as it
+/// takes, after the application segments (such as JFIF or Exif) which
+/// are expected to come first.
+pub fn embed(bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let at = segments(bytes)?
+        .iter()
+        .find(|segment| !(0xE0..=0xEF).contains(&segment.marker))
+        .unwrap()
+        .start;
+
+    let mut embedded = bytes[..at].to_vec();
+    // An empty payload still gets a segment, so that it can be found
+    let parts = payload.chunks(SEGMENT_PAYLOAD_SIZE);
+    for part in parts.chain(payload.is_empty().then_some(&[][..])) {
+        let mut data = PAYLOAD_ID.to_vec();
+        data.extend(part);
+        embedded.extend(segment(PAYLOAD_MARKER, &data)?);
+    }
+    embedded.extend(&bytes[at..]);
+    Ok(embedded)
+}
+
+/// Removes every impass payload from the JPEG in `bytes`,
+/// keeping whatever trails its EOI marker.
+pub fn strip(bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let mut stripped = bytes[..2].to_vec();
+    let mut at = 2;
+    for segment in segments(bytes)? {
+        if !is_payload(bytes, &segment) {
+            stripped.extend(&bytes[at..segment.end]);
+        }
+        at = segment.end;
+    }
+    stripped.extend(&bytes[at..]);
+    Ok(stripped)
+}
+
+mod test {
+    /// A tiny JPEG-like file, with an EOI lookalike within a
+    /// comment and stuffed bytes within its entropy-coded data.
+    #[allow(dead_code)]
+    fn tricky_jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend(super::segment(0xFE, &[b'h', 0xFF, 0xD9, b'i']).unwrap());
+        bytes.extend(super::segment(0xDA, &[1, 2, 3]).unwrap());
+        bytes.extend([0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD3, 0x56]);
+        bytes.extend([0xFF, 0xD9]);
+        bytes
+    }
+
+    #[test]
+    fn parses_segments() {
+        let bytes = std::fs::read("images/kekw.jpg").unwrap();
+        let segments = super::segments(&bytes).unwrap();
+
+        assert_eq!(segments[0].marker, 0xE1);
+        assert_eq!(segments.last().unwrap().marker, super::EOI);
+        assert_eq!(segments.last().unwrap().end, bytes.len());
+    }
+
+    #[test]
+    fn detects_the_real_eoi_marker() {
+        let mut bytes = tricky_jpeg();
+        let len = bytes.len();
+        bytes.extend(b"trailing data");
+
+        assert_eq!(super::end(&bytes), Ok(len - 2));
+        assert_eq!(super::strip(&bytes), Ok(bytes.clone()));
+    }
+
+    #[test]
+    fn keeps_trailing_data() {
+        let mut bytes = std::fs::read("images/kekw.jpg").unwrap();
+        bytes.extend(b"trailing data");
+
+        let embedded = super::embed(&bytes, b"payload").unwrap();
+
+        assert!(embedded.ends_with(b"trailing data"));
+        assert_eq!(super::strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn plain_images_have_no_payload() {
+        let bytes = std::fs::read("images/kekw.jpg").unwrap();
+
+        assert_eq!(super::extract(&bytes), Ok(None));
+    }
+
+    #[test]
+    fn embeds_and_strips_payloads() {
+        let bytes = std::fs::read("images/kekw.jpg").unwrap();
+
+        let embedded = super::embed(&bytes, b"payload").unwrap();
+
+        assert_eq!(super::extract(&embedded), Ok(Some(b"payload".to_vec())));
+        // Application segments come first
+        assert_eq!(super::segments(&embedded).unwrap()[0].marker, 0xE1);
+        assert_eq!(super::strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn spreads_large_payloads_over_several_segments() {
+        let bytes = std::fs::read("images/kekw.jpg").unwrap();
+        let payload = (0..150_000).map(|idx| idx as u8).collect::<Vec<u8>>();
+
+        let embedded = super::embed(&bytes, &payload).unwrap();
+        let parts = super::segments(&embedded)
+            .unwrap()
+            .iter()
+            .filter(|segment| super::is_payload(&embedded, segment))
+            .count();
+
+        assert_eq!(parts, 3);
+        assert_eq!(super::extract(&embedded), Ok(Some(payload)));
+        assert_eq!(super::strip(&embedded), Ok(bytes.clone()));
+
+        let empty = super::embed(&bytes, b"").unwrap();
+        assert_eq!(super::extract(&empty), Ok(Some(vec![])));
+    }
+
+    #[test]
+    #[should_panic(expected = "File is not a valid JPEG: it is truncated!")]
+    fn rejects_truncated_images() {
+        let bytes = std::fs::read("images/kekw.jpg").unwrap();
+
+        crate::or_exit!(super::segments(&bytes[..bytes.len() - 2]));
+    }
+}
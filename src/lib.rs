@@ -0,0 +1,177 @@
+//! ImPass stores secrets within images (and a few other file formats),
+//! encrypted with the env parameters and an optional password.
+//!
+//! ```no_run
+//! let options = impass::Options {
+//!     password: Some("pass123".to_string()),
+//!     ..Default::default()
+//! };
+//!
+//! let image = std::fs::read("images/cat.png").unwrap();
+//! let encoded = impass::encode(&image, b"Hello, World!", &options).unwrap();
+//! assert_eq!(impass::decode(&encoded, &options).unwrap(), b"Hello, World!");
+//! ```
+
+mod bmp;
+pub mod carrier;
+pub mod cli;
+pub(crate) mod config;
+pub mod error;
+mod gif;
+pub mod impass;
+mod jpeg;
+mod macros;
+mod png;
+pub(crate) mod reading;
+mod riff;
+mod stego;
+pub mod utils;
+mod wav;
+mod webp;
+
+pub use error::ImpassError;
+pub use impass::inspect::{Metadata, Report};
+pub use impass::vault::Entry;
+
+/// How secrets get stored into a file, or got back out of it.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub password: Option<String>,
+    /// The env parameters, as collected by `utils::env::try_collect_env`.
+    pub env: utils::Env,
+    /// Failed password attempts newly encoded files allow
+    /// before destroying themselves, 0 meaning unlimited.
+    pub max_attempts: u8,
+    /// Whether to hide the secrets within the samples (such as pixels)
+    /// of the file instead of a chunk of its own.
+    pub lsb: bool,
+    /// Whether to overwrite the secrets the file already holds when encoding.
+    pub replace: bool,
+}
+
+impl Default for Options {
+    /// No password, with the default env parameters.
+    fn default() -> Self {
+        Self {
+            password: None,
+            env: utils::env::try_collect_env(utils::Env::new()).unwrap_or_default(),
+            max_attempts: 0,
+            lsb: false,
+            replace: false,
+        }
+    }
+}
+
+/// Encodes the `secret` into the file in `content`, yielding the encoded file.
+/// Files which already hold secrets are refused, unless `options.replace` is set.
+pub fn encode(content: &[u8], secret: &[u8], options: &Options) -> Result<Vec<u8>, ImpassError> {
+    let mut content = content.to_vec();
+    // Derived once, both to look for secrets and to hide the new one
+    let order = impass::decoder::order_key(options)?;
+    if let Some((_, location)) = impass::decoder::find_with(&content, options, order.as_deref())? {
+        if !options.replace {
+            return Err(ImpassError::AlreadyHoldsSecrets);
+        }
+        content = impass::strip::remove(&content, &location)?;
+    }
+
+    let entries = [Entry::new(impass::vault::DEFAULT_ENTRY, secret)];
+    impass::encoder::seal_with(&content, &entries, options, order.as_deref())
+}
+
+/// Decodes the secret stored within the file in `content`. Failed password
+/// attempts are not recorded, see `impass::decoder::open`.
+pub fn decode(content: &[u8], options: &Options) -> Result<Vec<u8>, ImpassError> {
+    impass::decoder::pick(impass::decoder::open(content, options)?)
+}
+
+/// Reports on the file in `content` without decrypting anything.
+/// Secrets hidden within its samples can only be found with the
+/// password from the `options`, if any.
+pub fn inspect(content: &[u8], options: &Options) -> Result<Report, ImpassError> {
+    impass::inspect::inspect(content, options)
+}
+
+mod test {
+    #[allow(dead_code)]
+    fn with_password(password: &str) -> super::Options {
+        super::Options {
+            password: Some(password.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn encodes_and_decodes_without_exiting() {
+        let image = std::fs::read("images/gradient.bmp").unwrap();
+        let options = with_password("pass123");
+
+        let encoded = super::encode(&image, b"library secret", &options).unwrap();
+
+        assert_eq!(
+            super::decode(&encoded, &options),
+            Ok(b"library secret".to_vec())
+        );
+        assert_eq!(
+            super::decode(&encoded, &with_password("wrong")),
+            Err(super::ImpassError::WrongPassword)
+        );
+        assert_eq!(
+            super::decode(&encoded, &super::Options::default()),
+            Err(super::ImpassError::PasswordRequired)
+        );
+    }
+
+    #[test]
+    fn refuses_files_holding_secrets_unless_replacing() {
+        let image = std::fs::read("images/gradient.bmp").unwrap();
+        let mut options = super::Options::default();
+        let encoded = super::encode(&image, b"first!", &options).unwrap();
+
+        assert_eq!(
+            super::encode(&encoded, b"second!", &options),
+            Err(super::ImpassError::AlreadyHoldsSecrets)
+        );
+
+        options.replace = true;
+        let encoded = super::encode(&encoded, b"second!", &options).unwrap();
+        assert_eq!(super::decode(&encoded, &options), Ok(b"second!".to_vec()));
+    }
+
+    #[test]
+    fn reports_unusable_files_as_errors() {
+        let image = std::fs::read("images/gradient.bmp").unwrap();
+        let options = super::Options::default();
+
+        assert_eq!(
+            super::decode(&image, &options),
+            Err(super::ImpassError::NoSecrets)
+        );
+        assert_eq!(
+            super::decode(b"definitely not an image", &options),
+            Err(super::ImpassError::UnsupportedFormat)
+        );
+        assert_eq!(
+            super::inspect(&image, &options).map(|report| report.payload),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn reports_invalid_env_parameters_as_errors() {
+        let image = std::fs::read("images/gradient.bmp").unwrap();
+        let encoded = super::encode(&image, b"secret", &super::Options::default()).unwrap();
+        let mut options = super::Options::default();
+        options.env.remove("shift");
+
+        assert_eq!(
+            super::encode(&image, b"secret", &options),
+            Err(super::ImpassError::InvalidEnv("SHIFT"))
+        );
+        options.env = crate::utils::Env::from([("xor".to_string(), "-1".to_string())]);
+        assert_eq!(
+            super::decode(&encoded, &options),
+            Err(super::ImpassError::InvalidEnv("SHIFT"))
+        );
+    }
+}
@@ -1,11 +1,11 @@
 /// A generic error facilitation macro.
-/// Errors and exits with status 1.
+/// Errors (on stderr) and exits with status 1.
 #[macro_export]
 macro_rules! error {
 		( $( $x:expr ),* ) => {
 				{
 						$(
-								println!("[ERROR]: {}", $x);
+								eprintln!("[ERROR]: {}", $x);
 
 								#[cfg(not(test))]
 								std::process::exit(1);
@@ -26,10 +26,22 @@ macro_rules! unreachable {
 }
 
 /// A generic info facilitation macro.
-/// Just a fancy-er printf.
+/// Just a fancy-er printf, to stderr so that stdout only holds what was asked for.
 #[macro_export]
 macro_rules! info {
 		( $( $x:expr ),* ) => {{
-						$(println!("[INFO]: {}", $x))*
+						$(eprintln!("[INFO]: {}", $x))*
 		}};
 }
+
+/// Unwraps the result of a library call,
+/// erroring (see `error!`) with its `ImpassError`.
+#[macro_export]
+macro_rules! or_exit {
+    ( $x:expr ) => {
+        match $x {
+            Ok(value) => value,
+            Err(err) => $crate::error!(err.to_string()),
+        }
+    };
+}
@@ -0,0 +1,650 @@
+use crate::error::ImpassError;
+use crate::utils;
+
+/// The signature every PNG file starts with.
+pub static SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The chunk type impass payloads are stored in. Going by the case
+/// of each letter, it is an ancillary (i), private (m), conforming (P)
+/// and safe-to-copy (s) chunk, so image tools may keep it around.
+pub static PAYLOAD_CHUNK: [u8; 4] = *b"imPs";
+
+/// A single chunk of a PNG file, as positions within its bytes.
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    /// Where the chunk (its length field) starts.
+    pub start: usize,
+    pub kind: [u8; 4],
+    pub data: std::ops::Range<usize>,
+}
+
+impl Chunk {
+    /// Where the chunk (its CRC field) ends.
+    pub fn end(&self) -> usize {
+        self.data.end + 4
+    }
+}
+
+/// Parses the chunks of the PNG in `bytes`, up to and including IEND,
+/// verifying the CRC of every one of them.
+pub fn chunks(bytes: &[u8]) -> Result<Vec<Chunk>, ImpassError> {
+    if bytes.len() < SIGNATURE.len() || bytes[..SIGNATURE.len()] != SIGNATURE {
+        return Err(ImpassError::invalid("PNG", "it has no PNG signature"));
+    }
+
+    let mut chunks = Vec::new();
+    let mut at = SIGNATURE.len();
+    loop {
+        if bytes.len() < at + 12 {
+            return Err(ImpassError::invalid("PNG", "it is truncated"));
+        }
+
+        let len = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+        let kind: [u8; 4] = bytes[at + 4..at + 8].try_into().unwrap();
+        let data = at + 8..at + 8 + len;
+        if bytes.len() < data.end + 4 {
+            return Err(ImpassError::invalid("PNG", "it is truncated"));
+        }
+
+        let crc = u32::from_be_bytes(bytes[data.end..data.end + 4].try_into().unwrap());
+        if utils::crc32(&bytes[at + 4..data.end]) != crc {
+            return Err(ImpassError::invalid(
+                "PNG",
+                format!("its {} chunk is corrupted", String::from_utf8_lossy(&kind)),
+            ));
+        }
+
+        let chunk = Chunk {
+            start: at,
+            kind,
+            data,
+        };
+        at = chunk.end();
+        chunks.push(chunk);
+
+        if &kind == b"IEND" {
+            return Ok(chunks);
+        }
+    }
+}
+
+/// Serializes a chunk of the given `kind` holding `data`.
+pub fn chunk(kind: &[u8; 4], data: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let len = u32::try_from(data.len()).map_err(|_| ImpassError::TooLarge { limit: None })?;
+
+    let mut bytes = len.to_be_bytes().to_vec();
+    bytes.extend(kind);
+    bytes.extend(data);
+
+    let crc = utils::crc32(&bytes[4..]);
+    bytes.extend(crc.to_be_bytes());
+    Ok(bytes)
+}
+
+/// Finds where the impass payload lives within the PNG in `bytes`:
+/// the data of the payload chunk or, for images encoded before
+/// payloads were stored in chunks, the legacy payload trailing IEND.
+pub fn find_payload(bytes: &[u8]) -> Result<Option<std::ops::Range<usize>>, ImpassError> {
+    let chunks = chunks(bytes)?;
+
+    if let Some(chunk) = chunks.iter().find(|chunk| chunk.kind == PAYLOAD_CHUNK) {
+        return Ok(Some(chunk.data.clone()));
+    }
+
+    let end = chunks.last().unwrap().end();
+    if is_legacy_payload(&bytes[end..]) {
+        Ok(Some(end..bytes.len()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Whether the bytes trailing IEND are laid out the way images were
+/// encoded before payloads were stored in chunks: a has-pass byte, the
+/// bcrypt hash of the password (if any) and the base64-encoded secret,
+/// both with single-byte lengths, and nothing else. Other tools append
+/// all sorts of data there, which is none of our business.
+fn is_legacy_payload(trailer: &[u8]) -> bool {
+    use base64::prelude::*;
+
+    let field = |at: usize| -> Option<&[u8]> {
+        let len = *trailer.get(at)? as usize;
+        trailer.get(at + 1..at + 1 + len)
+    };
+
+    let at = match trailer.first() {
+        Some(0) => 1,
+        Some(1) => match field(1) {
+            Some(crypt) => crypt.len() + 2,
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    match field(at) {
+        Some(secret) => {
+            at + 1 + secret.len() == trailer.len() && BASE64_STANDARD.decode(secret).is_ok()
+        }
+        None => false,
+    }
+}
+
+/// Stores the `payload` in a payload chunk right before IEND.
+pub fn embed(bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let iend = chunks(bytes)?.last().unwrap().start;
+
+    let mut embedded = bytes[..iend].to_vec();
+    embedded.extend(chunk(&PAYLOAD_CHUNK, payload)?);
+    embedded.extend(&bytes[iend..]);
+    Ok(embedded)
+}
+
+/// Removes every impass payload from the PNG in `bytes`,
+/// keeping whatever else trails IEND.
+pub fn strip(bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let chunks = chunks(bytes)?;
+
+    let mut stripped = bytes[..SIGNATURE.len()].to_vec();
+    for chunk in &chunks {
+        if chunk.kind != PAYLOAD_CHUNK {
+            stripped.extend(&bytes[chunk.start..chunk.end()]);
+        }
+    }
+
+    let end = chunks.last().unwrap().end();
+    if !is_legacy_payload(&bytes[end..]) {
+        stripped.extend(&bytes[end..]);
+    }
+    Ok(stripped)
+}
+
+/// PNG images, storing payloads in a chunk of their own or,
+/// when asked to, hiding them within their pixels.
+pub struct Png;
+
+impl crate::carrier::Carrier for Png {
+    fn name(&self) -> &'static str {
+        "PNG"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(&SIGNATURE)
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        Ok(find_payload(bytes)?.map(|range| bytes[range].to_vec()))
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        embed(bytes, payload)
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        strip(bytes)
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        Ok(chunks(bytes)?.last().unwrap().start)
+    }
+
+    fn can_hide(&self, bytes: &[u8]) -> bool {
+        has_pixels(bytes)
+    }
+
+    fn reveal(&self, bytes: &[u8], keys: &[&[u8]]) -> Option<(usize, Vec<u8>)> {
+        reveal(bytes, keys)
+    }
+
+    fn hide(&self, bytes: &[u8], payload: &[u8], key: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        hide(bytes, payload, key)
+    }
+}
+
+/// Whether payloads can be hidden within the pixels of the PNG in `bytes`,
+/// telling from its header alone, without decoding its image data.
+pub fn has_pixels(bytes: &[u8]) -> bool {
+    Header::parse(bytes).is_ok()
+}
+
+/// Reveals the impass payload hidden within the pixels of the PNG in
+/// `bytes` with any of the given order `keys`, alongside the index of the
+/// key it was hidden with. The image data is decoded once for all of them.
+/// Yields `None` if there is none, which is also what a wrong key looks like.
+pub fn reveal(bytes: &[u8], keys: &[&[u8]]) -> Option<(usize, Vec<u8>)> {
+    let raster = Raster::decode(bytes).ok()?;
+
+    keys.iter().enumerate().find_map(|(idx, key)| {
+        let payload = crate::stego::reveal(&raster.data, &raster.positions(key))?;
+        payload
+            .starts_with(&crate::utils::constants::CONTAINER_MAGIC)
+            .then_some((idx, payload))
+    })
+}
+
+/// Hides the `payload` within the least significant bits of the pixels
+/// of the PNG in `bytes`, in the order shuffled with the given `key`,
+/// re-encoding its image data.
+pub fn hide(bytes: &[u8], payload: &[u8], key: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let mut raster = Raster::decode(bytes)?;
+
+    let positions = raster.positions(key);
+    crate::stego::hide(&mut raster.data, &positions, payload)?;
+    raster.encode(bytes)
+}
+
+/// Why secrets cannot be hidden within the pixels of an image.
+fn cannot_hide(reason: impl Into<String>) -> ImpassError {
+    ImpassError::CannotHide {
+        format: "PNG",
+        reason: Some(reason.into()),
+    }
+}
+
+/// The layout of the pixels of a non-interlaced, 8 or 16 bit PNG,
+/// as given by its header.
+struct Header {
+    height: usize,
+    /// Size of a single scanline.
+    stride: usize,
+    /// Size of a single pixel.
+    pixel_size: usize,
+    /// Size of a single sample (channel of a pixel).
+    sample_size: usize,
+    /// The number of channels holding color, skipping alpha.
+    colors: usize,
+}
+
+impl Header {
+    /// Parses the header of the PNG in `bytes`, refusing
+    /// the images whose pixels cannot hide anything.
+    fn parse(bytes: &[u8]) -> Result<Self, ImpassError> {
+        let chunks = chunks(bytes)?;
+        let header = &bytes[chunks[0].data.clone()];
+        if chunks[0].kind != *b"IHDR" || header.len() != 13 {
+            return Err(cannot_hide("the image has no valid header."));
+        }
+
+        let width = u32::from_be_bytes(header[0..4].try_into().unwrap()) as usize;
+        let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+        let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+
+        // Grayscale, RGB, grayscale with alpha and RGBA
+        let (channels, colors) = match color_type {
+            0 => (1, 1),
+            2 => (3, 3),
+            4 => (2, 1),
+            6 => (4, 3),
+            3 => return Err(cannot_hide("palette images are not supported.")),
+            _ => return Err(cannot_hide(format!("unknown color type {color_type}."))),
+        };
+        if bit_depth != 8 && bit_depth != 16 {
+            return Err(cannot_hide(format!(
+                "{bit_depth}-bit images are not supported."
+            )));
+        }
+        if interlace != 0 {
+            return Err(cannot_hide("interlaced images are not supported."));
+        }
+
+        let sample_size = bit_depth as usize / 8;
+        let pixel_size = channels * sample_size;
+        if width == 0 || height == 0 {
+            return Err(cannot_hide("the image has no pixels."));
+        }
+        // Every scanline starts with its filter type byte
+        let stride = width
+            .checked_mul(pixel_size)
+            .filter(|stride| {
+                stride
+                    .checked_add(1)
+                    .and_then(|line| line.checked_mul(height))
+                    .is_some()
+            })
+            .ok_or_else(|| cannot_hide("the image is too large."))?;
+
+        Ok(Self {
+            height,
+            stride,
+            pixel_size,
+            sample_size,
+            colors,
+        })
+    }
+}
+
+/// The unfiltered pixels of a non-interlaced, 8 or 16 bit PNG.
+struct Raster {
+    /// Every scanline, without its filter type byte.
+    data: Vec<u8>,
+    /// Size of a single scanline.
+    stride: usize,
+    /// Size of a single pixel.
+    pixel_size: usize,
+    /// Size of a single sample (channel of a pixel).
+    sample_size: usize,
+    /// The number of channels holding color, skipping alpha.
+    colors: usize,
+}
+
+impl Raster {
+    /// Inflates and unfilters the image data of the PNG in `bytes`.
+    fn decode(bytes: &[u8]) -> Result<Self, ImpassError> {
+        let Header {
+            height,
+            stride,
+            pixel_size,
+            sample_size,
+            colors,
+        } = Header::parse(bytes)?;
+        let size = (stride + 1) * height;
+
+        let idat = chunks(bytes)?
+            .iter()
+            .filter(|chunk| chunk.kind == *b"IDAT")
+            .flat_map(|chunk| &bytes[chunk.data.clone()])
+            .copied()
+            .collect::<Vec<u8>>();
+
+        // Inflating no more than the image can hold, so that a small
+        // image data stream cannot blow up into gigabytes
+        let filtered = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&idat, size)
+            .map_err(|_| cannot_hide("its image data is corrupted."))?;
+        if filtered.len() < size {
+            return Err(cannot_hide("its image data is truncated."));
+        }
+
+        let mut data = vec![0u8; height * stride];
+        for row in 0..height {
+            let line = &filtered[row * (stride + 1)..(row + 1) * (stride + 1)];
+            let (filter, line) = (line[0], &line[1..]);
+
+            for (idx, byte) in line.iter().enumerate() {
+                let at = row * stride + idx;
+                let left = if idx >= pixel_size {
+                    data[at - pixel_size]
+                } else {
+                    0
+                };
+                let up = if row > 0 { data[at - stride] } else { 0 };
+                let up_left = if row > 0 && idx >= pixel_size {
+                    data[at - stride - pixel_size]
+                } else {
+                    0
+                };
+
+                data[at] = byte.wrapping_add(match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    4 => paeth(left, up, up_left),
+                    _ => return Err(cannot_hide(format!("unknown filter type {filter}."))),
+                });
+            }
+        }
+
+        Ok(Self {
+            data,
+            stride,
+            pixel_size,
+            sample_size,
+            colors,
+        })
+    }
+
+    /// The bytes which may carry hidden bits, in the order shuffled with
+    /// the `key`: the least significant byte of every color sample. Alpha
+    /// is left untouched, as changes to fully transparent pixels would be
+    /// easy to spot.
+    fn positions(&self, key: &[u8]) -> Vec<usize> {
+        let mut positions = (0..self.data.len() / self.pixel_size)
+            .flat_map(|pixel| {
+                (0..self.colors)
+                    .map(move |color| pixel * self.pixel_size + (color + 1) * self.sample_size - 1)
+            })
+            .collect::<Vec<usize>>();
+
+        crate::stego::shuffle(&mut positions, key);
+        positions
+    }
+
+    /// Replaces the image data of the PNG in `bytes` with these pixels.
+    fn encode(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let mut filtered = Vec::with_capacity(self.data.len() + self.data.len() / self.stride);
+        for line in self.data.chunks(self.stride) {
+            filtered.push(0);
+            filtered.extend(line);
+        }
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, 9);
+
+        let mut encoded = bytes[..SIGNATURE.len()].to_vec();
+        let mut written = false;
+        for chunk in chunks(bytes)? {
+            if chunk.kind != *b"IDAT" {
+                encoded.extend(&bytes[chunk.start..chunk.end()]);
+            } else if !written {
+                for data in compressed.chunks(IDAT_SIZE) {
+                    encoded.extend(self::chunk(b"IDAT", data)?);
+                }
+                written = true;
+            }
+        }
+        Ok(encoded)
+    }
+}
+
+/// Size of the image data chunks written when re-encoding an image.
+const IDAT_SIZE: usize = 64 * 1024;
+
+/// The Paeth predictor of the PNG specification.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let (to_left, to_up, to_up_left) = (
+        (estimate - left as i16).abs(),
+        (estimate - up as i16).abs(),
+        (estimate - up_left as i16).abs(),
+    );
+
+    if to_left <= to_up && to_left <= to_up_left {
+        left
+    } else if to_up <= to_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+mod test {
+    #[test]
+    fn parses_chunks() {
+        let bytes = std::fs::read("images/cat.png").unwrap();
+        let chunks = super::chunks(&bytes).unwrap();
+
+        assert_eq!(&chunks[0].kind, b"IHDR");
+        assert_eq!(chunks[0].data.len(), 13);
+        assert_eq!(&chunks.last().unwrap().kind, b"IEND");
+        assert_eq!(chunks.last().unwrap().end(), bytes.len());
+    }
+
+    #[test]
+    fn plain_images_have_no_payload() {
+        let bytes = std::fs::read("images/cat.png").unwrap();
+
+        assert_eq!(super::find_payload(&bytes), Ok(None));
+    }
+
+    #[test]
+    fn finds_trailing_payloads() {
+        let bytes = std::fs::read("tests/nopass.png").unwrap();
+        let range = super::find_payload(&bytes).unwrap().unwrap();
+
+        assert_eq!(range.end, bytes.len());
+        assert_eq!(&bytes[range.start - 8..range.start - 4], b"IEND");
+    }
+
+    #[test]
+    fn ignores_other_trailing_data() {
+        let mut bytes = std::fs::read("images/cat.png").unwrap();
+        bytes.extend(b"garbage trailing");
+
+        assert_eq!(super::find_payload(&bytes), Ok(None));
+
+        let embedded = super::embed(&bytes, b"payload").unwrap();
+        assert_eq!(super::strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn strips_trailing_payloads() {
+        let bytes = std::fs::read("tests/nopass.png").unwrap();
+        let range = super::find_payload(&bytes).unwrap().unwrap();
+
+        assert_eq!(super::strip(&bytes), Ok(bytes[..range.start].to_vec()));
+    }
+
+    #[test]
+    fn embeds_and_strips_payloads() {
+        let bytes = std::fs::read("images/cat.png").unwrap();
+
+        let embedded = super::embed(&bytes, b"payload").unwrap();
+        let range = super::find_payload(&embedded).unwrap().unwrap();
+
+        assert_eq!(&embedded[range], b"payload");
+        assert_eq!(
+            &super::chunks(&embedded).unwrap().last().unwrap().kind,
+            b"IEND"
+        );
+        assert_eq!(super::strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn refuses_image_data_larger_than_the_image() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+        let chunks = super::chunks(&bytes).unwrap();
+
+        // The same image data, claiming to be a single pixel
+        let mut header = bytes[chunks[0].data.clone()].to_vec();
+        header[0..8].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+        let mut shrunk = bytes[..super::SIGNATURE.len()].to_vec();
+        shrunk.extend(super::chunk(b"IHDR", &header).unwrap());
+        shrunk.extend(&bytes[chunks[0].end()..]);
+
+        assert!(super::has_pixels(&bytes));
+        assert!(super::Raster::decode(&shrunk).is_err());
+
+        // Dimensions which overflow the size of the raster
+        header[0..8].copy_from_slice(&[0xFF; 8]);
+        let mut huge = bytes[..super::SIGNATURE.len()].to_vec();
+        huge.extend(super::chunk(b"IHDR", &header).unwrap());
+        huge.extend(&bytes[chunks[0].end()..]);
+
+        assert!(super::Raster::decode(&huge).is_err());
+    }
+
+    #[test]
+    fn tells_whether_pixels_can_hide_from_the_header_alone() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+        let chunks = super::chunks(&bytes).unwrap();
+        let header = bytes[chunks[0].data.clone()].to_vec();
+
+        let with = |header: &[u8], idat: &[u8]| {
+            let mut image = bytes[..super::SIGNATURE.len()].to_vec();
+            image.extend(super::chunk(b"IHDR", header).unwrap());
+            image.extend(super::chunk(b"IDAT", idat).unwrap());
+            image.extend(super::chunk(b"IEND", &[]).unwrap());
+            image
+        };
+
+        // Never inflated, so corrupted image data goes unnoticed
+        let corrupted = with(&header, b"not zlib");
+        assert!(super::has_pixels(&corrupted));
+        assert!(super::Raster::decode(&corrupted).is_err());
+
+        let mut palette = header.clone();
+        palette[9] = 3;
+        assert!(!super::has_pixels(&with(&palette, b"not zlib")));
+
+        let mut interlaced = header.clone();
+        interlaced[12] = 1;
+        assert!(!super::has_pixels(&with(&interlaced, b"not zlib")));
+
+        let mut shallow = header;
+        shallow[8] = 4;
+        assert!(!super::has_pixels(&with(&shallow, b"not zlib")));
+    }
+
+    #[test]
+    #[should_panic(expected = "File is not a valid PNG: its imPs chunk is corrupted!")]
+    fn rejects_corrupted_chunks() {
+        let bytes = std::fs::read("images/cat.png").unwrap();
+        let mut embedded = super::embed(&bytes, b"payload").unwrap();
+
+        let range = super::find_payload(&embedded).unwrap().unwrap();
+        embedded[range.start] ^= 1;
+
+        crate::or_exit!(super::find_payload(&embedded));
+    }
+
+    #[test]
+    fn hides_payloads_within_pixels() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+        let original = super::Raster::decode(&bytes).unwrap();
+
+        let mut payload = crate::utils::constants::CONTAINER_MAGIC.to_vec();
+        payload.extend(b"payload");
+        let hidden = super::hide(&bytes, &payload, b"key").unwrap();
+
+        assert_eq!(super::find_payload(&hidden), Ok(None));
+        assert_eq!(
+            super::reveal(&hidden, &[b"other key", b"key"]),
+            Some((1, payload))
+        );
+        assert_eq!(super::reveal(&hidden, &[b"other key"]), None);
+
+        // Pixels changed by at most their least significant bit
+        let raster = super::Raster::decode(&hidden).unwrap();
+        for (new, old) in raster.data.iter().zip(original.data) {
+            assert!(new ^ old <= 1);
+        }
+    }
+
+    #[test]
+    fn spreads_hidden_payloads_over_the_image() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+        let original = super::Raster::decode(&bytes).unwrap();
+
+        let hidden = super::hide(&bytes, &[0xFF; 32], b"key").unwrap();
+        let raster = super::Raster::decode(&hidden).unwrap();
+
+        // Changes do not sit at the top of the image
+        let changed = (0..raster.data.len())
+            .filter(|idx| raster.data[*idx] != original.data[*idx])
+            .collect::<Vec<usize>>();
+        assert!(*changed.last().unwrap() > raster.data.len() / 2);
+    }
+
+    #[test]
+    fn plain_pixels_hold_no_payload() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+
+        assert!(super::has_pixels(&bytes));
+        assert_eq!(super::reveal(&bytes, &[b"key"]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The file is too small to hide your secrets")]
+    fn refuses_payloads_over_pixel_capacity() {
+        let bytes = std::fs::read("images/gradient.png").unwrap();
+
+        crate::or_exit!(super::hide(&bytes, &vec![42; 160 * 120 * 3 / 8], b"key"));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot hide secrets within this PNG file: palette images are not supported."
+    )]
+    fn refuses_palette_images() {
+        let bytes = std::fs::read("images/cat.png").unwrap();
+
+        crate::or_exit!(super::hide(&bytes, b"payload", b"key"));
+    }
+}
@@ -1,8 +1,8 @@
+use crate::carrier;
 use crate::config;
-use crate::utils::{constants, index_vec};
 
 /// Verifies that the given `filename` exists
-/// and is a path to a PNG image.
+/// and is a path to a file (image or audio) of a supported format.
 /// If so - yields the bytecode contents to the caller buffer.
 fn read_and_verify_input(content: &mut Vec<u8>, filename: &String) {
     let file = std::fs::read(filename);
@@ -11,29 +11,28 @@ fn read_and_verify_input(content: &mut Vec<u8>, filename: &String) {
     }
     let text = file.unwrap();
 
-    let png_signature: Vec<u8> = vec![137, 80, 78, 71, 13, 10, 26, 10];
-    if text[0..8] != png_signature {
-        crate::error!(format!("Image provided is not a PNG: {filename}"));
+    if carrier::detect(&text).is_none() {
+        crate::error!(format!(
+            "File provided is not of a supported format ({}): {filename}",
+            carrier::supported()
+        ));
     }
 
     content.extend(text);
 }
 
-/// Checks the bytecode content of a given file. If your file does not
-/// start AND end with a PNG signature - you've got problems. :)
+/// Reads the bytecode content of a given file, parsing its
+/// chunks (or segments). If your file does not end with an IEND chunk
+/// (or EOI marker) - you've got problems. :)
+/// Yields the position of the IEND chunk (or EOI marker).
 pub fn gimme_bytecode(config: &config::Config, content: &mut Vec<u8>) -> usize {
     read_and_verify_input(content, &config.file_to_read().clone().unwrap());
 
-    if let Some(idx) = index_vec(content, &constants::EOF_SIGNATURE) {
-        idx
-    } else {
-        crate::error!(format!(
-            "File is not a valid PNG: {}",
-            config.file_to_read().clone().unwrap()
-        ));
-    }
+    let carrier = crate::or_exit!(carrier::of(content));
+    crate::or_exit!(carrier.end(content))
 }
 
+#[allow(clippy::len_zero)]
 mod test {
     #[test]
     fn verify_correct_input() {
@@ -44,10 +43,20 @@ mod test {
     }
 
     #[test]
-    #[should_panic(expected = "Image provided is not a PNG: images/kekw.jpg")]
-    fn verify_incorrect_input() {
+    fn verify_jpeg_input() {
         let mut content = Vec::new();
         super::read_and_verify_input(&mut content, &String::from("images/kekw.jpg"));
+
+        assert!(content.len() > 0) // no panics
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "File provided is not of a supported format (PNG, JPEG, WebP, GIF, WAV, BMP): README.md"
+    )]
+    fn verify_incorrect_input() {
+        let mut content = Vec::new();
+        super::read_and_verify_input(&mut content, &String::from("README.md"));
         // should have paniced
         // thus content is not changed
         assert!(content.len() == 0)
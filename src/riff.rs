@@ -0,0 +1,191 @@
+use crate::error::ImpassError;
+
+/// The header every RIFF file starts with, before its size and form type.
+pub static SIGNATURE: [u8; 4] = *b"RIFF";
+
+/// The chunk impass payloads are stored in, within any RIFF file.
+pub static PAYLOAD_CHUNK: [u8; 4] = *b"imps";
+
+/// A single chunk of a RIFF file, as positions within its bytes.
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    /// Where the chunk (its type) starts.
+    pub start: usize,
+    pub kind: [u8; 4],
+    pub data: std::ops::Range<usize>,
+}
+
+impl Chunk {
+    /// Where the chunk ends, including the padding
+    /// byte which follows data of odd sizes.
+    pub fn end(&self) -> usize {
+        self.data.end + self.data.len() % 2
+    }
+}
+
+/// Whether `bytes` are a RIFF file of the given `form` type.
+pub fn detect(bytes: &[u8], form: &[u8; 4]) -> bool {
+    bytes.len() >= 12 && bytes[..4] == SIGNATURE && bytes[8..12] == *form
+}
+
+/// Parses the chunks of the RIFF file of the given `form` type in
+/// `bytes`, up to the size in its header. `name` is the name of the
+/// format, as shown in errors.
+pub fn chunks(bytes: &[u8], form: &[u8; 4], name: &'static str) -> Result<Vec<Chunk>, ImpassError> {
+    if !detect(bytes, form) {
+        return Err(ImpassError::invalid(
+            name,
+            format!("it has no {name} signature"),
+        ));
+    }
+
+    let size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    if bytes.len() < 8 + size {
+        return Err(ImpassError::invalid(name, "it is truncated"));
+    }
+
+    let mut chunks = Vec::new();
+    let mut at = 12;
+    while at < 8 + size {
+        if 8 + size < at + 8 {
+            return Err(ImpassError::invalid(name, "it is truncated"));
+        }
+
+        let len = u32::from_le_bytes(bytes[at + 4..at + 8].try_into().unwrap()) as usize;
+        let chunk = Chunk {
+            start: at,
+            kind: bytes[at..at + 4].try_into().unwrap(),
+            data: at + 8..at + 8 + len,
+        };
+        if 8 + size < chunk.data.end {
+            return Err(ImpassError::invalid(name, "it is truncated"));
+        }
+
+        at = chunk.end();
+        chunks.push(chunk);
+    }
+
+    if chunks.is_empty() {
+        return Err(ImpassError::invalid(name, "it is empty"));
+    }
+    Ok(chunks)
+}
+
+/// Serializes a chunk of the given `kind` holding `data`.
+pub fn chunk(kind: &[u8; 4], data: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let len = u32::try_from(data.len()).map_err(|_| ImpassError::TooLarge { limit: None })?;
+
+    let mut bytes = kind.to_vec();
+    bytes.extend(len.to_le_bytes());
+    bytes.extend(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    Ok(bytes)
+}
+
+/// Serializes a RIFF file of the given `form` type out of the given
+/// serialized `chunks`, followed by the `trailer` past its size.
+pub fn file(form: &[u8; 4], chunks: &[Vec<u8>], trailer: &[u8]) -> Vec<u8> {
+    let body = chunks.concat();
+
+    let mut bytes = SIGNATURE.to_vec();
+    bytes.extend((body.len() as u32 + 4).to_le_bytes());
+    bytes.extend(form);
+    bytes.extend(body);
+    bytes.extend(trailer);
+    bytes
+}
+
+/// Whatever trails the last of the parsed `chunks` of the RIFF file in
+/// `bytes`, past the size in its header. Other tools may append data
+/// there, which is kept as-is.
+pub fn trailer<'a>(bytes: &'a [u8], chunks: &[Chunk]) -> &'a [u8] {
+    let end = chunks.last().map_or(bytes.len(), Chunk::end);
+    bytes.get(end..).unwrap_or_default()
+}
+
+/// Extracts the data of the payload chunk of the RIFF file in `bytes`.
+pub fn extract(
+    bytes: &[u8],
+    form: &[u8; 4],
+    name: &'static str,
+) -> Result<Option<Vec<u8>>, ImpassError> {
+    Ok(chunks(bytes, form, name)?
+        .into_iter()
+        .find(|chunk| chunk.kind == PAYLOAD_CHUNK)
+        .map(|chunk| bytes[chunk.data].to_vec()))
+}
+
+/// Removes every payload chunk from the RIFF file in `bytes`,
+/// keeping whatever trails the size in its header.
+pub fn strip(bytes: &[u8], form: &[u8; 4], name: &'static str) -> Result<Vec<u8>, ImpassError> {
+    let parsed = chunks(bytes, form, name)?;
+    let chunks = parsed
+        .iter()
+        .filter(|chunk| chunk.kind != PAYLOAD_CHUNK)
+        .map(|chunk| bytes[chunk.start..chunk.end()].to_vec())
+        .collect::<Vec<Vec<u8>>>();
+
+    Ok(file(form, &chunks, trailer(bytes, &parsed)))
+}
+
+mod test {
+    #[test]
+    fn pads_odd_chunks() {
+        let bytes = super::file(
+            b"TEST",
+            &[
+                super::chunk(b"odd ", b"abc").unwrap(),
+                super::chunk(b"even", b"ab").unwrap(),
+            ],
+            &[],
+        );
+        let chunks = super::chunks(&bytes, b"TEST", "test").unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(&bytes[chunks[0].data.clone()], b"abc");
+        assert_eq!(chunks[1].start, chunks[0].end());
+        assert_eq!(chunks[1].end(), bytes.len());
+    }
+
+    #[test]
+    fn extracts_and_strips_payloads() {
+        let plain = super::file(b"TEST", &[super::chunk(b"data", b"abc").unwrap()], &[]);
+        let bytes = super::file(
+            b"TEST",
+            &[
+                super::chunk(b"data", b"abc").unwrap(),
+                super::chunk(&super::PAYLOAD_CHUNK, b"payload").unwrap(),
+            ],
+            &[],
+        );
+
+        assert_eq!(super::extract(&plain, b"TEST", "test"), Ok(None));
+        assert_eq!(
+            super::extract(&bytes, b"TEST", "test"),
+            Ok(Some(b"payload".to_vec()))
+        );
+        assert_eq!(super::strip(&bytes, b"TEST", "test"), Ok(plain));
+    }
+
+    #[test]
+    fn keeps_trailing_data() {
+        let chunks = [super::chunk(b"data", b"abc").unwrap()];
+        let plain = super::file(b"TEST", &chunks, b"trailing data");
+        let bytes = super::file(
+            b"TEST",
+            &[
+                chunks[0].clone(),
+                super::chunk(&super::PAYLOAD_CHUNK, b"payload").unwrap(),
+            ],
+            b"trailing data",
+        );
+
+        assert_eq!(
+            super::trailer(&bytes, &super::chunks(&bytes, b"TEST", "test").unwrap()),
+            b"trailing data"
+        );
+        assert_eq!(super::strip(&bytes, b"TEST", "test"), Ok(plain));
+    }
+}
@@ -0,0 +1,150 @@
+/// Size of the length prefix hidden in front of every payload.
+const LENGTH_SIZE: usize = 4;
+
+/// The number of payload bytes that can be hidden
+/// within the given number of carrier `positions`.
+pub fn capacity(positions: usize) -> usize {
+    (positions / 8).saturating_sub(LENGTH_SIZE)
+}
+
+/// Shuffles the carrier `positions` into an order only known to
+/// holders of the `key`, so that hidden bits are spread all over the
+/// carrier instead of sitting at its start. Fisher-Yates, fed by
+/// SHA-256 over the key and a counter.
+pub fn shuffle(positions: &mut [usize], key: &[u8]) {
+    use sha2::{Digest, Sha256};
+
+    let (mut counter, mut block, mut used) = (0u64, [0u8; 32], 32);
+    let mut random = || {
+        if used == block.len() {
+            block = Sha256::new()
+                .chain_update(key)
+                .chain_update(counter.to_be_bytes())
+                .finalize()
+                .into();
+            counter += 1;
+            used = 0;
+        }
+        used += 8;
+        u64::from_be_bytes(block[used - 8..used].try_into().unwrap())
+    };
+
+    for idx in (1..positions.len()).rev() {
+        let other = (random() % (idx as u64 + 1)) as usize;
+        positions.swap(idx, other);
+    }
+}
+
+/// Hides the `payload`, prefixed with its 32-bit length, within the least
+/// significant bits of the `carrier` bytes at the given `positions`.
+pub fn hide(
+    carrier: &mut [u8],
+    positions: &[usize],
+    payload: &[u8],
+) -> Result<(), crate::error::ImpassError> {
+    if payload.len() > capacity(positions.len()) {
+        return Err(crate::error::ImpassError::TooSmall {
+            needed: payload.len(),
+            capacity: capacity(positions.len()),
+        });
+    }
+
+    let mut framed = (payload.len() as u32).to_be_bytes().to_vec();
+    framed.extend(payload);
+
+    let bits = framed
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1));
+    for (position, bit) in positions.iter().zip(bits) {
+        carrier[*position] = (carrier[*position] & !1) | bit;
+    }
+    Ok(())
+}
+
+/// Reveals a payload hidden by `hide`. Yields `None` if the
+/// hidden length cannot fit, meaning nothing was hidden there.
+pub fn reveal(carrier: &[u8], positions: &[usize]) -> Option<Vec<u8>> {
+    let read = |from: usize, len: usize| {
+        positions[from * 8..(from + len) * 8]
+            .chunks(8)
+            .map(|bits| {
+                bits.iter()
+                    .fold(0u8, |byte, position| (byte << 1) | (carrier[*position] & 1))
+            })
+            .collect::<Vec<u8>>()
+    };
+
+    if positions.len() < LENGTH_SIZE * 8 {
+        return None;
+    }
+
+    let len = u32::from_be_bytes(read(0, LENGTH_SIZE).try_into().unwrap()) as usize;
+    if len > capacity(positions.len()) {
+        return None;
+    }
+
+    Some(read(LENGTH_SIZE, len))
+}
+
+mod test {
+    #[test]
+    fn hides_and_reveals() {
+        let mut carrier = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        let original = carrier.clone();
+        let positions = (0..carrier.len()).step_by(2).collect::<Vec<usize>>();
+
+        super::hide(&mut carrier, &positions, b"hidden!").unwrap();
+
+        assert_eq!(
+            super::reveal(&carrier, &positions),
+            Some(b"hidden!".to_vec())
+        );
+        // Only the least significant bits of the given positions changed
+        for (idx, (new, old)) in carrier.iter().zip(original).enumerate() {
+            assert!(new ^ old <= 1);
+            assert!(positions.contains(&idx) || *new == old);
+        }
+    }
+
+    #[test]
+    fn shuffles_by_key() {
+        let positions = (0..1000).collect::<Vec<usize>>();
+        let shuffled = |key: &[u8]| {
+            let mut shuffled = positions.clone();
+            super::shuffle(&mut shuffled, key);
+            shuffled
+        };
+
+        assert_eq!(shuffled(b"key"), shuffled(b"key"));
+        assert_ne!(shuffled(b"key"), shuffled(b"other key"));
+        assert_ne!(shuffled(b"key"), positions);
+
+        let mut sorted = shuffled(b"key");
+        sorted.sort();
+        assert_eq!(sorted, positions);
+    }
+
+    #[test]
+    fn capacity_works() {
+        assert_eq!(super::capacity(0), 0);
+        assert_eq!(super::capacity(32), 0);
+        assert_eq!(super::capacity(800), 96);
+    }
+
+    #[test]
+    #[should_panic(expected = "The file is too small to hide your secrets")]
+    fn refuses_payloads_over_capacity() {
+        let mut carrier = vec![0u8; 100];
+        let positions = (0..carrier.len()).collect::<Vec<usize>>();
+
+        crate::or_exit!(super::hide(&mut carrier, &positions, &[42; 9]));
+    }
+
+    #[test]
+    fn reveals_nothing_from_plain_carriers() {
+        let carrier = vec![1u8; 100];
+        let positions = (0..carrier.len()).collect::<Vec<usize>>();
+
+        assert_eq!(super::reveal(&carrier, &positions), None);
+    }
+}
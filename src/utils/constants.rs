@@ -1,4 +1,21 @@
-pub static EOF_SIGNATURE: [u8; 12] = [0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130];
+/// Magic tag every impass payload container starts with.
+pub static CONTAINER_MAGIC: [u8; 4] = *b"IMPS";
 
-#[allow(dead_code)]
-pub const BCRYPT_COST: u32 = 15;
+/// The latest payload container format version this impass can read and write.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Argon2id memory cost (in KiB) new images derive their keys with.
+pub const KDF_MEMORY: u32 = 19 * 1024;
+/// Argon2id iterations new images derive their keys with.
+pub const KDF_ITERATIONS: u32 = 2;
+/// Argon2id lanes new images derive their keys with.
+pub const KDF_PARALLELISM: u32 = 1;
+
+/// The most Argon2id memory (in KiB, 1 GiB) a file can ask keys to be
+/// derived with. The parameters are read before anything is authenticated,
+/// so a crafted file could otherwise exhaust the memory of whoever opens it.
+pub const KDF_MAX_MEMORY: u32 = 1024 * 1024;
+/// The most Argon2id iterations a file can ask keys to be derived with.
+pub const KDF_MAX_ITERATIONS: u32 = 16;
+/// The most Argon2id lanes a file can ask keys to be derived with.
+pub const KDF_MAX_PARALLELISM: u32 = 16;
@@ -9,40 +9,64 @@ fn derand_bytes(x: u32, sh: u32, pepper: u32, gn: u32, xor: u32) -> u32 {
     rand_bytes(x ^ pepper, sh, pepper, gn, xor) ^ pepper
 }
 
+use crate::error::ImpassError;
 use base64::prelude::*;
 
+/// The names of the env parameters every image is encoded with.
+const ENV_PARAMS: [&str; 3] = ["SHIFT", "GODNUM", "XOR"];
+
+/// The value of the env parameter `name` (such as "SHIFT"), as collected
+/// by `env::try_collect_env`, erroring unless it holds a valid number.
+fn env_param<'a>(env: &'a super::Env, name: &'static str) -> Result<&'a str, ImpassError> {
+    env.get(&name.to_lowercase())
+        .map(String::as_str)
+        .filter(|value| value.parse::<u32>().is_ok())
+        .ok_or(ImpassError::InvalidEnv(name))
+}
+
+/// The env parameters the legacy scheme is controlled by.
+fn legacy_params(env: &super::Env) -> Result<[u32; 3], ImpassError> {
+    let mut params = [0; 3];
+    for (param, name) in params.iter_mut().zip(ENV_PARAMS) {
+        *param = env_param(env, name)?
+            .parse()
+            .map_err(|_| ImpassError::InvalidEnv(name))?;
+    }
+    Ok(params)
+}
+
 /// Encrypts the given secret using the given env
-/// parameters as controls.
-/// `secret` - The string to encrypt. Supports only ASCII characters.
+/// parameters as controls. This is the legacy scheme images
+/// were encoded with before the container existed.
+/// `secret` - The bytes to encrypt, treated as opaque.
 /// `env` - The environment variables used as a control.
 /// # Examples:
-/// ```rust
+/// ```ignore
 /// let env = collect_env(Env::new());
-/// let crypt = encrypt_secret("Hello, world!", env);
-/// assert_ne!(crypt, "Hello, world!");
+/// let crypt = encrypt_secret("Hello, world!".as_bytes(), env)?;
+/// assert_ne!(crypt, b"Hello, world!");
 /// ```
-pub fn encrypt_secret(secret: &str, env: &super::Env) -> Vec<u8> {
-    let shift: u32 = env[&"shift".to_string()].clone().parse().unwrap();
-    let godnum: u32 = env[&"godnum".to_string()].clone().parse().unwrap();
-    let xor: u32 = env[&"xor".to_string()].clone().parse().unwrap();
+pub fn encrypt_secret(secret: &[u8], env: &super::Env) -> Result<Vec<u8>, ImpassError> {
+    let [shift, godnum, xor] = legacy_params(env)?;
 
+    // Legacy images stored their secrets reversed
     let st = secret
-        .as_bytes()
         .iter()
+        .rev()
         .enumerate()
         .map(|(idx, ch)| {
-            (self::rand_bytes(
+            self::rand_bytes(
                 *ch as u32,
                 super::within_range(shift as u8, 1, 31) as u32,
                 super::within_range(idx as u8, 1, 8) as u32,
                 godnum,
                 xor,
             ) as u8
-                ^ super::within_range(shift as u8, 7, 30)) as char
+                ^ super::within_range(shift as u8, 7, 30)
         })
-        .collect::<String>();
+        .collect::<Vec<u8>>();
 
-    BASE64_STANDARD.encode(st).into()
+    Ok(BASE64_STANDARD.encode(st).into())
 }
 
 /// Decrypts the given encrypted secret using the given env
@@ -50,34 +74,203 @@ pub fn encrypt_secret(secret: &str, env: &super::Env) -> Vec<u8> {
 /// `encrypted` - The encrypted secret to decrypt.  
 /// `env` - The environment variables used as a control.  
 /// # Examples:
-/// ```rust
+/// ```ignore
 /// let env = collect_env(Env::new());
-/// let crypt = encrypt_secret("Hello, world!", env);
-/// let decrypt = dencrypt_secret(crypt, env);
-/// assert_ne!(decrypt, "Hello, world!");
+/// let crypt = encrypt_secret("Hello, world!".as_bytes(), env)?;
+/// let decrypt = decrypt_secret(crypt, env)?;
+/// assert_eq!(decrypt, b"Hello, world!");
 /// ```
-pub fn decrypt_secret(encrypted: &[u8], env: &super::Env) -> String {
-    let shift: u32 = env[&"shift".to_string()].clone().parse().unwrap();
-    let godnum: u32 = env[&"godnum".to_string()].clone().parse().unwrap();
-    let xor: u32 = env[&"xor".to_string()].clone().parse().unwrap();
+pub fn decrypt_secret(encrypted: &[u8], env: &super::Env) -> Result<Vec<u8>, ImpassError> {
+    let [shift, godnum, xor] = legacy_params(env)?;
 
-    BASE64_STANDARD
+    let decoded = BASE64_STANDARD
         .decode(encrypted)
-        .unwrap()
+        .map_err(|_| ImpassError::Damaged)?;
+
+    Ok(decoded
         .iter()
         .enumerate()
         .map(|(idx, ch)| {
-            (self::derand_bytes(
+            self::derand_bytes(
                 *ch as u32,
                 super::within_range(shift as u8, 1, 31) as u32,
                 super::within_range(idx as u8, 1, 8) as u32,
                 godnum,
                 xor,
             ) as u8
-                ^ super::within_range(shift as u8, 7, 30)) as char
+                ^ super::within_range(shift as u8, 7, 30)
         })
         .rev()
-        .collect()
+        .collect())
+}
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use sha2::{Digest, Sha256};
+
+/// Size of the random salt every image gets its key derived with.
+pub const SALT_SIZE: usize = 16;
+/// Size of the random nonce every image is encrypted with.
+pub const NONCE_SIZE: usize = 12;
+
+/// Generates `size` cryptographically secure random bytes.
+pub fn random_bytes(size: usize) -> Result<Vec<u8>, ImpassError> {
+    let mut bytes = vec![0u8; size];
+    getrandom::getrandom(&mut bytes).map_err(|_| ImpassError::Randomness)?;
+    Ok(bytes)
+}
+
+/// Argon2id parameters a key was derived with,
+/// stored alongside the payload they protect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KdfParams {
+    /// Memory cost in KiB.
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory: super::constants::KDF_MEMORY,
+            iterations: super::constants::KDF_ITERATIONS,
+            parallelism: super::constants::KDF_PARALLELISM,
+        }
+    }
+}
+
+impl KdfParams {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.memory, self.iterations, self.parallelism]
+            .iter()
+            .flat_map(|param| param.to_be_bytes())
+            .collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImpassError> {
+        if bytes.len() != 12 {
+            return Err(ImpassError::Truncated);
+        }
+
+        let param =
+            |idx: usize| u32::from_be_bytes(bytes[idx * 4..idx * 4 + 4].try_into().unwrap());
+        let params = Self {
+            memory: param(0),
+            iterations: param(1),
+            parallelism: param(2),
+        };
+
+        use super::constants::{KDF_MAX_ITERATIONS, KDF_MAX_MEMORY, KDF_MAX_PARALLELISM};
+        if params.memory > KDF_MAX_MEMORY
+            || params.iterations > KDF_MAX_ITERATIONS
+            || params.parallelism > KDF_MAX_PARALLELISM
+        {
+            return Err(ImpassError::InvalidKdf);
+        }
+        Ok(params)
+    }
+}
+
+/// The env parameters every key is bound to.
+fn env_material(env: &super::Env) -> Result<Vec<u8>, ImpassError> {
+    let mut material = Vec::new();
+    for name in ENV_PARAMS {
+        material.extend(env_param(env, name)?.as_bytes());
+        material.push(0);
+    }
+    Ok(material)
+}
+
+/// Size of the fingerprint of the env parameters stored within images.
+pub const FINGERPRINT_SIZE: usize = 4;
+
+/// Fingerprints the env parameters an image gets encoded with, so that
+/// decoding it with other ones can be told apart from a wrong password.
+/// Salted with the per-image `salt` and truncated, it gives nothing away
+/// about the parameters themselves.
+pub fn env_fingerprint(salt: &[u8], env: &super::Env) -> Result<Vec<u8>, ImpassError> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"impass/env-fingerprint");
+    hasher.update(salt);
+    hasher.update(env_material(env)?);
+
+    Ok(hasher.finalize()[..FINGERPRINT_SIZE].to_vec())
+}
+
+/// Derives a per-image encryption key with Argon2id from the user's
+/// `password` (if any) and the env parameters.
+/// `salt` - The per-image salt, at least 8 bytes long.
+/// `kdf` - The Argon2id parameters to use.
+/// `env` - The environment variables used as a control.
+pub fn derive_key(
+    password: Option<&str>,
+    salt: &[u8],
+    kdf: &KdfParams,
+    env: &super::Env,
+) -> Result<Key, ImpassError> {
+    let params = argon2::Params::new(kdf.memory, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|_| ImpassError::InvalidKdf)?;
+
+    let argon = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut input = password.unwrap_or_default().as_bytes().to_vec();
+    input.push(0);
+    input.extend(env_material(env)?);
+
+    let mut key = Key::default();
+    argon
+        .hash_password_into(&input, salt, &mut key)
+        .map_err(|_| ImpassError::KeyDerivation)?;
+    Ok(key)
+}
+
+/// Salt the pixel order key is derived with. It cannot be random, as
+/// the order has to be known before anything hidden in an image is.
+const ORDER_SALT: &[u8; 16] = b"impass/lsb-order";
+
+/// Derives the key the order of the samples hiding a payload is
+/// shuffled with, from the user's `password` (if any) and the env
+/// parameters. Without it the hidden bits cannot even be located.
+pub fn derive_order_key(password: Option<&str>, env: &super::Env) -> Result<Key, ImpassError> {
+    derive_key(password, ORDER_SALT, &KdfParams::default(), env)
+}
+
+/// Encrypts and authenticates the given `plain` bytes with
+/// ChaCha20-Poly1305, additionally authenticating `aad`.
+/// `key` - The per-image key, see `derive_key`.
+/// `nonce` - The per-image nonce, must never be reused with the same key.
+/// Only fails for plain bytes too large for the cipher.
+pub fn encrypt_payload(
+    plain: &[u8],
+    aad: &[u8],
+    key: &Key,
+    nonce: &[u8],
+) -> Result<Vec<u8>, ImpassError> {
+    let cipher = ChaCha20Poly1305::new(key);
+
+    cipher
+        .encrypt(Nonce::from_slice(nonce), Payload { msg: plain, aad })
+        .map_err(|_| ImpassError::TooLarge { limit: None })
+}
+
+/// Decrypts bytes produced by `encrypt_payload`, yielding `None`
+/// if they (or the `aad`) have been tampered with or the key is wrong.
+pub fn try_decrypt_payload(
+    crypt: &[u8],
+    aad: &[u8],
+    key: &Key,
+    nonce: &[u8],
+) -> Result<Option<Vec<u8>>, ImpassError> {
+    if nonce.len() != NONCE_SIZE {
+        return Err(ImpassError::Truncated);
+    }
+
+    let cipher = ChaCha20Poly1305::new(key);
+
+    Ok(cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: crypt, aad })
+        .ok())
 }
 
 mod test {
@@ -98,20 +291,188 @@ mod test {
             "hello world!",
             "how are youuuuu!?",
             "this is some text I'm boutta encode",
+            "здравей, свят!",
+            "café ☕ and 🦀🔑",
         ];
 
         let env = super::super::env::collect_env(super::super::Env::new());
 
         for txt in texts {
-            let crypted = super::encrypt_secret(txt, &env)
-                .iter()
-                .map(|x| *x as char)
-                .collect::<String>();
+            let crypted = super::encrypt_secret(txt.as_bytes(), &env).unwrap();
+
+            assert_ne!(crypted, txt.as_bytes());
+
+            let decrypted = super::decrypt_secret(&crypted, &env).unwrap();
+            assert_eq!(decrypted, txt.as_bytes());
+        }
+    }
+
+    #[test]
+    fn encrypt_decrypt_works_on_any_bytes() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+
+        let env = super::super::env::collect_env(super::super::Env::new());
+        let crypted = super::encrypt_secret(&bytes, &env).unwrap();
+
+        assert_eq!(super::decrypt_secret(&crypted, &env).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encrypt_decrypt_payload_works() {
+        let env = super::super::env::collect_env(super::super::Env::new());
+        let salt = super::random_bytes(super::SALT_SIZE).unwrap();
+        let nonce = super::random_bytes(super::NONCE_SIZE).unwrap();
+        let key = super::derive_key(None, &salt, &super::KdfParams::default(), &env).unwrap();
+
+        let crypt = super::encrypt_payload(b"hello world!", b"aad", &key, &nonce).unwrap();
+        assert_ne!(crypt, b"hello world!");
+
+        let plain = super::try_decrypt_payload(&crypt, b"aad", &key, &nonce).unwrap();
+        assert_eq!(plain, Some(b"hello world!".to_vec()));
+    }
+
+    #[test]
+    fn decrypt_payload_detects_tampering() {
+        let env = super::super::env::collect_env(super::super::Env::new());
+        let salt = super::random_bytes(super::SALT_SIZE).unwrap();
+        let nonce = super::random_bytes(super::NONCE_SIZE).unwrap();
+        let key = super::derive_key(None, &salt, &super::KdfParams::default(), &env).unwrap();
+
+        let mut crypt = super::encrypt_payload(b"hello world!", b"aad", &key, &nonce).unwrap();
+        crypt[3] ^= 1;
+
+        assert_eq!(
+            super::try_decrypt_payload(&crypt, b"aad", &key, &nonce).unwrap(),
+            None
+        );
+        crypt[3] ^= 1;
+        assert_eq!(
+            super::try_decrypt_payload(&crypt, b"dda", &key, &nonce).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn derived_keys_depend_on_everything() {
+        let env = super::super::env::collect_env(super::super::Env::new());
+        let other_env = super::super::env::collect_env(super::super::Env::from([(
+            "XOR".to_string(),
+            "13".to_string(),
+        )]));
+        let salt = super::random_bytes(super::SALT_SIZE).unwrap();
+        let other_salt = super::random_bytes(super::SALT_SIZE).unwrap();
+        let kdf = super::KdfParams::default();
+
+        let key = super::derive_key(Some("pass123"), &salt, &kdf, &env);
+
+        assert_eq!(key, super::derive_key(Some("pass123"), &salt, &kdf, &env));
+        assert_ne!(key, super::derive_key(Some("pass124"), &salt, &kdf, &env));
+        assert_ne!(key, super::derive_key(None, &salt, &kdf, &env));
+        assert_ne!(
+            key,
+            super::derive_key(Some("pass123"), &other_salt, &kdf, &env)
+        );
+        assert_ne!(
+            key,
+            super::derive_key(Some("pass123"), &salt, &kdf, &other_env)
+        );
+    }
+
+    #[test]
+    fn env_fingerprints_depend_on_the_env_and_salt() {
+        let env = super::super::env::collect_env(super::super::Env::new());
+        let other_env = super::super::env::collect_env(super::super::Env::from([(
+            "GODNUM".to_string(),
+            "43".to_string(),
+        )]));
+        let salt = super::random_bytes(super::SALT_SIZE).unwrap();
+        let other_salt = super::random_bytes(super::SALT_SIZE).unwrap();
+
+        let fingerprint = super::env_fingerprint(&salt, &env).unwrap();
+
+        assert_eq!(fingerprint.len(), super::FINGERPRINT_SIZE);
+        assert_eq!(fingerprint, super::env_fingerprint(&salt, &env).unwrap());
+        assert_ne!(
+            fingerprint,
+            super::env_fingerprint(&salt, &other_env).unwrap()
+        );
+        assert_ne!(
+            fingerprint,
+            super::env_fingerprint(&other_salt, &env).unwrap()
+        );
+    }
+
+    #[test]
+    fn order_keys_depend_on_the_password() {
+        let env = crate::utils::env::collect_env(crate::utils::Env::new());
+
+        assert_eq!(
+            super::derive_order_key(Some("pass"), &env),
+            super::derive_order_key(Some("pass"), &env)
+        );
+        assert_ne!(
+            super::derive_order_key(Some("pass"), &env),
+            super::derive_order_key(None, &env)
+        );
+    }
+
+    #[test]
+    fn reports_invalid_env_parameters() {
+        let mut env = crate::utils::env::collect_env(crate::utils::Env::new());
+        env.remove("godnum");
+        env.insert("xor".to_string(), "not a number".to_string());
+
+        assert_eq!(
+            super::env_fingerprint(b"salt", &env),
+            Err(crate::error::ImpassError::InvalidEnv("GODNUM"))
+        );
+        env.insert("godnum".to_string(), "42".to_string());
+        assert_eq!(
+            super::decrypt_secret(b"", &env),
+            Err(crate::error::ImpassError::InvalidEnv("XOR"))
+        );
+    }
+
+    #[test]
+    fn kdf_params_round_trip() {
+        let kdf = super::KdfParams {
+            memory: 1024,
+            iterations: 3,
+            parallelism: 2,
+        };
+
+        assert_eq!(super::KdfParams::from_bytes(&kdf.to_bytes()), Ok(kdf));
+    }
+
+    #[test]
+    fn rejects_kdf_params_over_the_ceiling() {
+        use crate::utils::constants::{KDF_MAX_ITERATIONS, KDF_MAX_MEMORY, KDF_MAX_PARALLELISM};
 
-            assert_ne!(crypted, txt);
+        let ceiling = super::KdfParams {
+            memory: KDF_MAX_MEMORY,
+            iterations: KDF_MAX_ITERATIONS,
+            parallelism: KDF_MAX_PARALLELISM,
+        };
+        assert!(super::KdfParams::from_bytes(&ceiling.to_bytes()).is_ok());
 
-            let decrypted = super::decrypt_secret(crypted.as_bytes(), &env);
-            assert_eq!(decrypted.chars().rev().collect::<String>(), txt);
+        for kdf in [
+            super::KdfParams {
+                memory: u32::MAX - 1,
+                ..ceiling.clone()
+            },
+            super::KdfParams {
+                iterations: KDF_MAX_ITERATIONS + 1,
+                ..ceiling.clone()
+            },
+            super::KdfParams {
+                parallelism: KDF_MAX_PARALLELISM + 1,
+                ..ceiling.clone()
+            },
+        ] {
+            assert_eq!(
+                super::KdfParams::from_bytes(&kdf.to_bytes()),
+                Err(crate::error::ImpassError::InvalidKdf)
+            );
         }
     }
 }
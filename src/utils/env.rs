@@ -1,37 +1,35 @@
+use crate::error::ImpassError;
 use std::io::Write;
 
 /// Checks the values of provided environment variables,
 /// if they have been given by the user.
-fn check_env_vars(env: super::Env) -> super::Env {
-    if let Some(shr) = env.get("SHIFT") {
-        let val = shr.parse::<u32>();
-        if val.is_err() {
-            crate::error!("Number provided for SHIFT must be a positive whole number!")
+fn check_env_vars(env: super::Env) -> Result<super::Env, ImpassError> {
+    for name in ["SHIFT", "GODNUM", "XOR"] {
+        if env.get(name).is_some_and(|val| val.parse::<u32>().is_err()) {
+            return Err(ImpassError::InvalidEnv(name));
         }
     }
 
-    if let Some(godnum) = env.get("GODNUM") {
-        let val = godnum.parse::<u32>();
-        if val.is_err() {
-            crate::error!("Number provided for GODNUM must be a positive whole number!")
-        }
-    }
-
-    if let Some(xor) = env.get("XOR") {
-        let val = xor.parse::<u32>();
-        if val.is_err() {
-            crate::error!("Number provided for XOR must be a positive whole number!")
-        }
+    if env
+        .get("MAX_ATTEMPTS")
+        .is_some_and(|val| val.parse::<u8>().is_err())
+    {
+        return Err(ImpassError::InvalidEnv("MAX_ATTEMPTS"));
     }
 
-    env
+    Ok(env)
 }
 
 /// Checks, collects and formats the environment variables
 /// in a format that's easy to use by `impass`.
-pub fn collect_env(env: super::Env) -> super::Env {
-    let env = self::check_env_vars(env);
-    super::Env::from([
+pub(crate) fn collect_env(env: super::Env) -> super::Env {
+    crate::or_exit!(try_collect_env(env))
+}
+
+/// Like `collect_env`, yielding an error for invalid values instead.
+pub fn try_collect_env(env: super::Env) -> Result<super::Env, ImpassError> {
+    let env = self::check_env_vars(env)?;
+    Ok(super::Env::from([
         (
             "shift".to_string(),
             env.get("SHIFT").unwrap_or(&"11".to_string()).to_owned(),
@@ -44,24 +42,54 @@ pub fn collect_env(env: super::Env) -> super::Env {
             "xor".to_string(),
             env.get("XOR").unwrap_or(&"69".to_string()).to_owned(),
         ),
+        (
+            "max_attempts".to_string(),
+            env.get("MAX_ATTEMPTS")
+                .unwrap_or(&"0".to_string())
+                .to_owned(),
+        ),
         (
             "answers".to_string(),
             env.get("ANSWERS")
                 .unwrap_or(&"y,yes".to_string())
                 .to_owned(),
         ),
-    ])
+    ]))
+}
+
+/// Reads, checks and collects the environment variables from the
+/// env file at `path`, such as one made by `generate_env`.
+pub(crate) fn read_env_file(path: &str) -> super::Env {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        crate::error!(format!("Could not read the env file \"{path}\"!"));
+    };
+
+    let mut env = super::Env::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            crate::error!(format!("The env file \"{path}\" is malformed: {line}"));
+        };
+        env.insert(
+            key.trim().to_string(),
+            value.trim().trim_matches('"').to_string(),
+        );
+    }
+    collect_env(env)
 }
 
 fn check_autogen() {
     let env = std::env::vars().collect::<std::collections::HashMap<String, String>>();
     let env = collect_env(env);
 
-    let secret = "this is my test secret";
-    let encrypted = super::crypt::encrypt_secret(secret, &env);
-    let decrypted = super::crypt::decrypt_secret(&encrypted, &env);
+    let secret = b"this is my test secret";
+    let decrypted = super::crypt::encrypt_secret(secret, &env)
+        .and_then(|encrypted| super::crypt::decrypt_secret(&encrypted, &env));
 
-    if decrypted != secret {
+    if decrypted.as_deref() != Ok(&secret[..]) {
         // crate::info!("Generated config file was incorrect; retrying...");
         _ = generate_env();
     }
@@ -69,13 +97,14 @@ fn check_autogen() {
 
 /// Generates and populates a .env file with "random"
 /// parameters for the environment.
-pub fn generate_env() -> std::io::Result<()> {
+pub(crate) fn generate_env() -> std::io::Result<()> {
     let mut f = std::fs::File::create(".env")?;
 
     let buf = format!(
         "SHIFT={sh}
 GODNUM={gn}
 XOR={xor}
+MAX_ATTEMPTS=0
 ANSWERS=\"y,yes\"
 ",
         sh = super::within_range(super::rand(), 8, 16),
@@ -100,7 +129,37 @@ mod test {
 
         let res = super::check_env_vars(normal_env.clone());
 
-        assert_eq!(normal_env, res)
+        assert_eq!(Ok(normal_env), res)
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Number provided for MAX_ATTEMPTS must be a whole number between 0 and 255!"
+    )]
+    fn env_fails_max_attempts_check_correctly() {
+        let env = std::collections::HashMap::<String, String>::from([(
+            "MAX_ATTEMPTS".to_string(),
+            "256".to_string(),
+        )]);
+
+        let _ = super::collect_env(env);
+    }
+
+    #[test]
+    fn reads_env_files() {
+        let path = std::env::temp_dir().join("impass_reads_env_files.env");
+        std::fs::write(
+            &path,
+            "# rotated\nSHIFT=12\n\nXOR = 99\nANSWERS=\"y,yes,sure\"\n",
+        )
+        .unwrap();
+
+        let env = super::read_env_file(path.to_str().unwrap());
+
+        assert_eq!(env["shift"], "12");
+        assert_eq!(env["xor"], "99");
+        assert_eq!(env["godnum"], "42");
+        assert_eq!(env["answers"], "y,yes,sure");
     }
 
     #[test]
@@ -111,6 +170,6 @@ mod test {
             "invalid value here".to_string(),
         )]);
 
-        let _ = super::check_env_vars(normal_env.clone());
+        let _ = super::collect_env(normal_env.clone());
     }
 }
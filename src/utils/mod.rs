@@ -1,48 +1,172 @@
 pub mod constants;
 pub mod crypt;
 pub mod env;
-
-/// Gives the index of a `needle` within a
-/// given `haystack` if such exists.
-pub fn index_vec<T>(haystack: &[T], needle: &[T]) -> Option<usize>
-where
-    T: Sized + Eq + PartialEq + std::fmt::Debug,
-{
-    for (position, window) in haystack.windows(needle.len()).enumerate() {
-        if window == needle {
-            return Some(position);
-        }
-    }
-    None
-}
+pub(crate) mod source;
 
 /// Prompts the user for a string.
-pub fn prompt(message: &str) -> String {
-    print!("{message}: ");
+pub(crate) fn prompt(message: &str) -> String {
+    eprint!("{message}: ");
     use std::io;
-    use std::io::*;
-    let _ = std::io::stdout().flush();
 
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
     input.trim().to_string()
 }
 
+/// Prompts the user for a password, without echoing
+/// it back when reading from a terminal.
+pub(crate) fn prompt_password(message: &str) -> String {
+    eprint!("{message}: ");
+
+    let echo = EchoOff::new();
+    let mut input = String::new();
+    let read = std::io::stdin().read_line(&mut input);
+    if echo.is_some() {
+        // The newline typed by the user was not echoed either
+        eprintln!();
+    }
+    drop(echo);
+
+    if read.is_err() {
+        crate::error!("Could not read your password!");
+    }
+    input.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Prompts the user for a new password twice, so that a typo
+/// does not lock the secrets away. An empty one means no password.
+pub(crate) fn prompt_new_password() -> Option<String> {
+    let pass = prompt_password("Enter a password (leave empty for none)");
+    if pass.is_empty() {
+        return None;
+    }
+
+    if prompt_password("Enter the password again") != pass {
+        crate::error!("The passwords you entered do not match, nothing was encoded!");
+    }
+    Some(pass)
+}
+
+/// Turns off the echo of the terminal on stdin for as long as it lives,
+/// restoring its previous settings when dropped.
+struct EchoOff {
+    #[cfg(unix)]
+    previous: libc::termios,
+}
+
+impl EchoOff {
+    /// Yields nothing if stdin is not a terminal, which does not echo anyway.
+    #[cfg(unix)]
+    fn new() -> Option<Self> {
+        // SAFETY: termios is plain old data, filled in by tcgetattr
+        // before being read, and stdin stays open for the whole run.
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) != 1 {
+                return None;
+            }
+
+            let mut previous = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut previous) != 0 {
+                return None;
+            }
+
+            let mut silent = previous;
+            silent.c_lflag &= !(libc::ECHO | libc::ECHONL);
+            silent.c_lflag |= libc::ICANON;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &silent) != 0 {
+                return None;
+            }
+
+            Some(Self { previous })
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Option<Self> {
+        None
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings tcgetattr gave back for stdin.
+        #[cfg(unix)]
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.previous);
+        }
+    }
+}
+
+/// Writes the `secret` alone to stdout when the user asked to reveal it,
+/// so that it does not end up in scrollback, logs or screen shares by accident.
+pub(crate) fn show_secret(reveal: bool, secret: &[u8]) {
+    use std::io::{IsTerminal, Write};
+
+    if !reveal {
+        crate::info!(format!(
+            "Your secret ({} bytes) was decoded, but is not shown. Pass --reveal to print it.",
+            secret.len()
+        ));
+        return;
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let mut written = stdout.write_all(secret);
+    // Piped secrets are written as-is, byte for byte
+    if stdout.is_terminal() {
+        written = written.and_then(|_| writeln!(stdout));
+    }
+    if written.and_then(|_| stdout.flush()).is_err() {
+        crate::error!("Could not write your secret to stdout!");
+    }
+}
+
+/// Normalizes a password to its Unicode NFC form, so that the
+/// same passphrase typed on different keyboards matches.
+pub fn normalize_password(pass: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+
+    pass.nfc().collect()
+}
+
+/// Destroys the file at `path`, overwriting its contents with random
+/// bytes before removing it. Note that on copy-on-write or flash storage
+/// old blocks may still linger on disk.
+pub fn wipe_file(path: &str) {
+    use std::io::Write;
+
+    if let Ok(mut file) = std::fs::OpenOptions::new().write(true).open(path) {
+        let len = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        if let Ok(noise) = crypt::random_bytes(len as usize) {
+            let _ = file.write_all(&noise);
+        }
+        let _ = file.sync_all();
+    }
+
+    if std::fs::remove_file(path).is_err() {
+        crate::error!(format!("Could not destroy the file {path}!"));
+    }
+}
+
 /// Modified Jenkins Hash to work in conjunction
 /// with bcrypt. Allows for 11-characters max ASCII
 /// only string.
-pub fn impassible_hash(item: &String) -> u128 {
+/// Only used to check passwords of images encoded before the
+/// container existed, newer images have no such restrictions.
+pub fn impassible_hash(item: &String) -> Result<u128, crate::error::ImpassError> {
     // Allow ASCII-only characters, so that even passwords
     // like "Pa$_swOrd" work :)
-    if !item.chars().all(|c| char::is_ascii(&c)) {
-        crate::error!(
-            "Password provided contains invalid characters. Please, use ASCII-only characters!"
-        );
+    if !item.is_ascii() {
+        return Err(crate::error::ImpassError::InvalidPassword(
+            "Password provided contains invalid characters. Please, use ASCII-only characters!",
+        ));
     }
 
     if item.len() > 11 {
         // 11 so it can bother you hehe
-        crate::error!("Maximum password length is 11.");
+        return Err(crate::error::ImpassError::InvalidPassword(
+            "Maximum password length is 11.",
+        ));
     }
 
     let key = item.to_string();
@@ -56,7 +180,20 @@ pub fn impassible_hash(item: &String) -> u128 {
     hash += hash << 3;
     hash ^= hash >> 11;
     hash += hash << 15;
-    hash
+    Ok(hash)
+}
+
+/// Computes the CRC-32 (IEEE) checksum of the given bytes.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
 }
 
 /// Constraints a given number to bounds of min < x < max
@@ -88,17 +225,29 @@ mod test {
     }
 
     #[test]
-    fn index_vec_works() {
-        let nums = [1, 2, 3, 4, 5, 6];
-        let target = [3, 4, 5];
+    fn crc32_works() {
+        assert_eq!(super::crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(super::crc32(b"IEND"), 0xAE42_6082);
+    }
 
-        let idx = super::index_vec(&nums, &target);
+    #[test]
+    fn normalize_password_works() {
+        let decomposed = "и\u{0306}";
+        let precomposed = "й";
 
-        assert_eq!(idx, Some(2));
+        assert_ne!(decomposed, precomposed);
+        assert_eq!(super::normalize_password(decomposed), precomposed);
+        assert_eq!(super::normalize_password("Pa$_swOrd"), "Pa$_swOrd");
+    }
+
+    #[test]
+    fn wipe_file_works() {
+        let path = std::env::temp_dir().join("impass_wipe_file_works.txt");
+        std::fs::write(&path, "top secret").unwrap();
 
-        let idx = super::index_vec(&nums, &[4, 3]);
+        super::wipe_file(path.to_str().unwrap());
 
-        assert_eq!(idx, None);
+        assert!(!path.exists());
     }
 
     #[test]
@@ -106,7 +255,7 @@ mod test {
         let items = vec!["helloworld!", "Pa$_swOrd"];
 
         for it in items {
-            assert!(super::impassible_hash(&it.to_string()) > 100000);
+            assert!(super::impassible_hash(&it.to_string()).unwrap() > 100000);
         }
     }
 
@@ -115,7 +264,7 @@ mod test {
     fn impassible_has_strict_length() {
         let item = "my password is tooo long";
 
-        super::impassible_hash(&item.to_string());
+        crate::or_exit!(super::impassible_hash(&item.to_string()));
     }
 
     #[test]
@@ -125,6 +274,6 @@ mod test {
     fn impassible_has_strict_contents() {
         let item = "невалиден"; // "invalid" in bulgarian
 
-        super::impassible_hash(&item.to_string());
+        crate::or_exit!(super::impassible_hash(&item.to_string()));
     }
 }
@@ -0,0 +1,184 @@
+/// Where a secret or password gets read from, instead of asking for it.
+/// Given as `stdin`, `file:PATH`, `fd:N` or `env:NAME`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Stdin,
+    File(String),
+    /// A file descriptor inherited from the parent process.
+    Fd(i32),
+    /// An environment variable, by name.
+    Env(String),
+}
+
+impl Source {
+    /// Parses a source such as `file:secret.txt`.
+    pub fn parse(spec: &str) -> Option<Self> {
+        if spec == "stdin" {
+            return Some(Self::Stdin);
+        }
+
+        match spec.split_once(':')? {
+            ("file", path) if !path.is_empty() => Some(Self::File(path.to_string())),
+            ("fd", fd) => fd.parse().ok().filter(|fd| *fd >= 0).map(Self::Fd),
+            ("env", name) if !name.is_empty() => Some(Self::Env(name.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Reads everything the source holds, up to a single trailing newline,
+    /// so that `echo secret |` and files ending with a newline work as expected.
+    pub fn read(&self) -> Vec<u8> {
+        use std::io::Read;
+
+        let mut bytes = Vec::new();
+        let read = match self {
+            Self::Stdin => std::io::stdin().read_to_end(&mut bytes).map(|_| ()),
+            Self::File(path) => std::fs::read(path).map(|contents| bytes = contents),
+            Self::Fd(fd) => read_fd(*fd, &mut bytes),
+            Self::Env(name) => match std::env::var_os(name) {
+                Some(value) => {
+                    bytes = value.into_encoded_bytes();
+                    Ok(())
+                }
+                None => crate::error!(format!("The environment variable {name} is not set!")),
+            },
+        };
+        if let Err(err) = read {
+            crate::error!(format!("Could not read from {self}: {err}"));
+        }
+
+        if bytes.ends_with(b"\n") {
+            bytes.pop();
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+        }
+        bytes
+    }
+
+    /// Reads a password from the source, which has to be text.
+    pub fn read_password(&self) -> String {
+        let Ok(pass) = String::from_utf8(self.read()) else {
+            crate::error!(format!("The password read from {self} is not valid UTF-8!"));
+        };
+        if pass.is_empty() {
+            crate::error!(format!("The password read from {self} is empty!"));
+        }
+        pass
+    }
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Stdin => write!(f, "stdin"),
+            Self::File(path) => write!(f, "the file {path}"),
+            Self::Fd(fd) => write!(f, "file descriptor {fd}"),
+            Self::Env(name) => write!(f, "the environment variable {name}"),
+        }
+    }
+}
+
+/// Reads the inherited file descriptor `fd` to its end, leaving it open,
+/// as it may just as well be stdio or the source of something else.
+#[cfg(unix)]
+fn read_fd(fd: i32, bytes: &mut Vec<u8>) -> std::io::Result<()> {
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    // SAFETY: the descriptor is checked to be open first, and the file
+    // borrowing it is never dropped, so it never gets closed by impass.
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    file.read_to_end(bytes).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32, _bytes: &mut Vec<u8>) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "file descriptors are only supported on Unix",
+    ))
+}
+
+mod test {
+    #[test]
+    fn parses_every_source() {
+        assert_eq!(super::Source::parse("stdin"), Some(super::Source::Stdin));
+        assert_eq!(
+            super::Source::parse("file:secrets/github.txt"),
+            Some(super::Source::File("secrets/github.txt".to_string()))
+        );
+        assert_eq!(super::Source::parse("fd:3"), Some(super::Source::Fd(3)));
+        assert_eq!(
+            super::Source::parse("env:IMPASS_PASS"),
+            Some(super::Source::Env("IMPASS_PASS".to_string()))
+        );
+
+        for invalid in [
+            "",
+            "file:",
+            "fd:-1",
+            "fd:three",
+            "env:",
+            "secret.txt",
+            "url:x",
+        ] {
+            assert_eq!(super::Source::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn reads_files_without_their_trailing_newline() {
+        let path = std::env::temp_dir().join("impass_reads_sources.txt");
+        std::fs::write(&path, "hunter2\r\n").unwrap();
+
+        let source = super::Source::File(path.to_str().unwrap().to_string());
+
+        assert_eq!(source.read(), b"hunter2");
+        assert_eq!(source.read_password(), "hunter2");
+    }
+
+    #[test]
+    fn reads_environment_variables() {
+        std::env::set_var("IMPASS_READS_SOURCES", "line one\nline two\n");
+
+        let source = super::Source::Env("IMPASS_READS_SOURCES".to_string());
+
+        assert_eq!(source.read(), b"line one\nline two");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn leaves_file_descriptors_open() {
+        use std::os::unix::io::AsRawFd;
+
+        let path = std::env::temp_dir().join("impass_leaves_fds_open.txt");
+        std::fs::write(&path, "hunter2\n").unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+
+        let source = super::Source::Fd(file.as_raw_fd());
+
+        assert_eq!(source.read(), b"hunter2");
+        // Read to its end already, yet still open
+        assert_eq!(source.read(), b"");
+        assert_ne!(unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFD) }, -1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The environment variable IMPASS_NOT_SET is not set!")]
+    fn refuses_missing_environment_variables() {
+        let _ = super::Source::Env("IMPASS_NOT_SET".to_string()).read();
+    }
+
+    #[test]
+    #[should_panic(expected = "The password read from the file")]
+    fn refuses_empty_passwords() {
+        let path = std::env::temp_dir().join("impass_refuses_empty_passwords.txt");
+        std::fs::write(&path, "\n").unwrap();
+
+        let _ = super::Source::File(path.to_str().unwrap().to_string()).read_password();
+    }
+}
@@ -0,0 +1,213 @@
+use crate::error::ImpassError;
+use crate::riff;
+
+/// The form type of WAV files, following their RIFF header.
+pub static FORM: [u8; 4] = *b"WAVE";
+
+/// Format tags of integer PCM audio, plain and extensible.
+const PCM: u16 = 1;
+const EXTENSIBLE: u16 = 0xFFFE;
+
+/// PCM WAV audio, storing payloads in a chunk of their own or,
+/// when asked to, hiding them within its samples.
+pub struct Wav;
+
+impl crate::carrier::Carrier for Wav {
+    fn name(&self) -> &'static str {
+        "WAV"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        riff::detect(bytes, &FORM)
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        riff::extract(bytes, &FORM, self.name())
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let parsed = chunks(bytes)?;
+        let mut chunks = parsed
+            .iter()
+            .map(|chunk| bytes[chunk.start..chunk.end()].to_vec())
+            .collect::<Vec<Vec<u8>>>();
+        chunks.push(riff::chunk(&riff::PAYLOAD_CHUNK, payload)?);
+
+        Ok(riff::file(&FORM, &chunks, riff::trailer(bytes, &parsed)))
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        riff::strip(bytes, &FORM, self.name())
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        Ok(chunks(bytes)?.last().unwrap().end())
+    }
+
+    fn can_hide(&self, bytes: &[u8]) -> bool {
+        Samples::find(bytes).is_ok()
+    }
+
+    fn reveal(&self, bytes: &[u8], keys: &[&[u8]]) -> Option<(usize, Vec<u8>)> {
+        let samples = Samples::find(bytes).ok()?;
+
+        keys.iter().enumerate().find_map(|(idx, key)| {
+            let payload = crate::stego::reveal(bytes, &samples.positions(key))?;
+            payload
+                .starts_with(&crate::utils::constants::CONTAINER_MAGIC)
+                .then_some((idx, payload))
+        })
+    }
+
+    fn hide(&self, bytes: &[u8], payload: &[u8], key: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        let samples = Samples::find(bytes)?;
+
+        let mut hidden = bytes.to_vec();
+        crate::stego::hide(&mut hidden, &samples.positions(key), payload)?;
+        Ok(hidden)
+    }
+}
+
+/// Parses the chunks of the WAV in `bytes`.
+pub fn chunks(bytes: &[u8]) -> Result<Vec<riff::Chunk>, ImpassError> {
+    riff::chunks(bytes, &FORM, "WAV")
+}
+
+/// Where the integer PCM samples of a WAV file are.
+struct Samples {
+    data: std::ops::Range<usize>,
+    /// Size of a single sample, of a single channel.
+    sample_size: usize,
+}
+
+/// Why secrets cannot be hidden within the samples of an audio file.
+fn cannot_hide(reason: impl Into<String>) -> ImpassError {
+    ImpassError::CannotHide {
+        format: "WAV",
+        reason: Some(reason.into()),
+    }
+}
+
+impl Samples {
+    fn find(bytes: &[u8]) -> Result<Self, ImpassError> {
+        let chunks = chunks(bytes)?;
+        let Some(format) = chunks.iter().find(|chunk| chunk.kind == *b"fmt ") else {
+            return Err(cannot_hide("the file has no format chunk."));
+        };
+        let format = &bytes[format.data.clone()];
+        if format.len() < 16 {
+            return Err(cannot_hide("its format chunk is corrupted."));
+        }
+
+        let field = |at: usize| u16::from_le_bytes([format[at], format[at + 1]]);
+        // Extensible files tell their actual format tag in their sub-format
+        let tag = if field(0) == EXTENSIBLE && format.len() >= 26 {
+            field(24)
+        } else {
+            field(0)
+        };
+        if tag != PCM {
+            return Err(cannot_hide("only integer PCM audio is supported."));
+        }
+
+        let bits = field(14) as usize;
+        if bits == 0 || bits > 32 {
+            return Err(cannot_hide(format!(
+                "{bits}-bit samples are not supported."
+            )));
+        }
+
+        let Some(data) = chunks.iter().find(|chunk| chunk.kind == *b"data") else {
+            return Err(cannot_hide("the file has no audio data."));
+        };
+
+        Ok(Self {
+            data: data.data.clone(),
+            sample_size: bits.div_ceil(8),
+        })
+    }
+
+    /// The bytes which may carry hidden bits, in the order shuffled with the
+    /// `key`: the least significant byte of every sample, which comes first.
+    fn positions(&self, key: &[u8]) -> Vec<usize> {
+        let mut positions = self
+            .data
+            .clone()
+            .step_by(self.sample_size)
+            .take(self.data.len() / self.sample_size)
+            .collect::<Vec<usize>>();
+
+        crate::stego::shuffle(&mut positions, key);
+        positions
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::carrier::Carrier;
+
+    #[test]
+    fn plain_audio_has_no_payload() {
+        let bytes = std::fs::read("audio/beep.wav").unwrap();
+
+        assert!(super::Wav.detect(&bytes));
+        assert_eq!(super::Wav.extract(&bytes), Ok(None));
+        assert_eq!(super::Wav.reveal(&bytes, &[b"key"]), None);
+    }
+
+    #[test]
+    fn embeds_and_strips_payloads() {
+        let bytes = std::fs::read("audio/beep.wav").unwrap();
+
+        let embedded = super::Wav.embed(&bytes, b"payload").unwrap();
+
+        assert_eq!(super::Wav.extract(&embedded), Ok(Some(b"payload".to_vec())));
+        assert_eq!(super::Wav.strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn keeps_trailing_data() {
+        let mut bytes = std::fs::read("audio/beep.wav").unwrap();
+        bytes.extend(b"trailing data");
+
+        let embedded = super::Wav.embed(&bytes, b"payload").unwrap();
+
+        assert!(embedded.ends_with(b"trailing data"));
+        assert_eq!(super::Wav.extract(&embedded), Ok(Some(b"payload".to_vec())));
+        assert_eq!(super::Wav.strip(&embedded), Ok(bytes));
+    }
+
+    #[test]
+    fn hides_payloads_within_samples() {
+        let bytes = std::fs::read("audio/beep.wav").unwrap();
+        let mut payload = crate::utils::constants::CONTAINER_MAGIC.to_vec();
+        payload.extend(b"payload");
+
+        let hidden = super::Wav.hide(&bytes, &payload, b"key").unwrap();
+
+        assert_eq!(hidden.len(), bytes.len());
+        assert_eq!(super::Wav.extract(&hidden), Ok(None));
+        assert_eq!(
+            super::Wav.reveal(&hidden, &[b"other key", b"key"]),
+            Some((1, payload))
+        );
+        assert_eq!(super::Wav.reveal(&hidden, &[b"other key"]), None);
+
+        // Only the least significant byte of 16-bit samples changed, by a bit
+        let data = super::Samples::find(&bytes).unwrap().data;
+        for idx in data.clone() {
+            let changed = hidden[idx] ^ bytes[idx];
+            assert!(changed <= 1);
+            assert!(changed == 0 || (idx - data.start).is_multiple_of(2));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The file is too small to hide your secrets")]
+    fn refuses_payloads_over_sample_capacity() {
+        let bytes = std::fs::read("audio/beep.wav").unwrap();
+        let samples = super::Samples::find(&bytes).unwrap();
+
+        crate::or_exit!(super::Wav.hide(&bytes, &vec![42; samples.data.len() / 2 / 8], b"key"));
+    }
+}
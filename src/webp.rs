@@ -0,0 +1,184 @@
+use crate::error::ImpassError;
+use crate::riff;
+
+/// The form type of WebP files, following their RIFF header.
+pub static FORM: [u8; 4] = *b"WEBP";
+
+/// An empty chunk following the VP8X header when impass converted
+/// the file to the extended format, so stripping can convert it back.
+pub static CONVERTED_CHUNK: [u8; 4] = *b"impx";
+
+/// WebP images, storing payloads in a chunk of their own.
+pub struct Webp;
+
+impl crate::carrier::Carrier for Webp {
+    fn name(&self) -> &'static str {
+        "WebP"
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        riff::detect(bytes, &FORM)
+    }
+
+    fn extract(&self, bytes: &[u8]) -> Result<Option<Vec<u8>>, ImpassError> {
+        riff::extract(bytes, &FORM, self.name())
+    }
+
+    fn embed(&self, bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        embed(bytes, payload)
+    }
+
+    fn strip(&self, bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+        strip(bytes)
+    }
+
+    fn end(&self, bytes: &[u8]) -> Result<usize, ImpassError> {
+        Ok(chunks(bytes)?.last().unwrap().end())
+    }
+}
+
+/// Parses the chunks of the WebP in `bytes`.
+pub fn chunks(bytes: &[u8]) -> Result<Vec<riff::Chunk>, ImpassError> {
+    riff::chunks(bytes, &FORM, "WebP")
+}
+
+/// Stores the `payload` in a payload chunk at the end of the WebP in
+/// `bytes`. Only the extended file format allows for chunks other than
+/// the image itself, so simple files get converted to it.
+pub fn embed(bytes: &[u8], payload: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let parsed = chunks(bytes)?;
+
+    let mut chunks = Vec::new();
+    if parsed[0].kind != *b"VP8X" {
+        chunks.push(extended_header(bytes, &parsed[0])?);
+        chunks.push(riff::chunk(&CONVERTED_CHUNK, &[])?);
+    }
+    chunks.extend(
+        parsed
+            .iter()
+            .map(|chunk| bytes[chunk.start..chunk.end()].to_vec()),
+    );
+    chunks.push(riff::chunk(&riff::PAYLOAD_CHUNK, payload)?);
+
+    Ok(riff::file(&FORM, &chunks, riff::trailer(bytes, &parsed)))
+}
+
+/// Removes every payload chunk from the WebP in `bytes`, converting
+/// it back to the simple file format if impass converted it.
+pub fn strip(bytes: &[u8]) -> Result<Vec<u8>, ImpassError> {
+    let parsed = chunks(bytes)?;
+    let converted = parsed.iter().any(|chunk| chunk.kind == CONVERTED_CHUNK);
+
+    let chunks = parsed
+        .iter()
+        .filter(|chunk| chunk.kind != riff::PAYLOAD_CHUNK && chunk.kind != CONVERTED_CHUNK)
+        .filter(|chunk| !converted || chunk.kind != *b"VP8X")
+        .map(|chunk| bytes[chunk.start..chunk.end()].to_vec())
+        .collect::<Vec<Vec<u8>>>();
+
+    Ok(riff::file(&FORM, &chunks, riff::trailer(bytes, &parsed)))
+}
+
+/// Builds the VP8X chunk of the extended file format
+/// for the lossy (VP8) or lossless (VP8L) `image` chunk.
+fn extended_header(bytes: &[u8], image: &riff::Chunk) -> Result<Vec<u8>, ImpassError> {
+    let data = &bytes[image.data.clone()];
+
+    let (width, height, alpha) = match &image.kind {
+        b"VP8L" if data.len() >= 5 && data[0] == 0x2F => {
+            let bits = u32::from_le_bytes(data[1..5].try_into().unwrap());
+            (
+                (bits & 0x3FFF) + 1,
+                ((bits >> 14) & 0x3FFF) + 1,
+                bits >> 28 & 1 == 1,
+            )
+        }
+        b"VP8 " if data.len() >= 10 && data[3..6] == [0x9D, 0x01, 0x2A] => {
+            let dimension = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as u32;
+            (dimension(6) & 0x3FFF, dimension(8) & 0x3FFF, false)
+        }
+        _ => return Err(ImpassError::invalid("WebP", "its image chunk is corrupted")),
+    };
+    if width == 0 || height == 0 {
+        return Err(ImpassError::invalid("WebP", "its image has no pixels"));
+    }
+
+    let mut header = vec![if alpha { 0x10 } else { 0 }, 0, 0, 0];
+    header.extend(&(width - 1).to_le_bytes()[..3]);
+    header.extend(&(height - 1).to_le_bytes()[..3]);
+    riff::chunk(b"VP8X", &header)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use crate::carrier::Carrier;
+
+    #[test]
+    fn parses_chunks() {
+        let bytes = std::fs::read("images/pixel.webp").unwrap();
+        let chunks = super::chunks(&bytes).unwrap();
+
+        assert_eq!(&chunks[0].kind, b"VP8L");
+        assert_eq!(super::Webp.end(&bytes), Ok(bytes.len()));
+    }
+
+    #[test]
+    fn rejects_images_without_pixels() {
+        // A lossy frame of 0x16 pixels
+        let frame = [0, 0, 0, 0x9D, 0x01, 0x2A, 0, 0, 16, 0];
+        let mut bytes = b"RIFF\0\0\0\0WEBP".to_vec();
+        bytes.extend(crate::riff::chunk(b"VP8 ", &frame).unwrap());
+        let size = (bytes.len() as u32 - 8).to_le_bytes();
+        bytes[4..8].copy_from_slice(&size);
+
+        assert_eq!(
+            super::Webp.embed(&bytes, b"payload"),
+            Err(crate::error::ImpassError::invalid(
+                "WebP",
+                "its image has no pixels"
+            ))
+        );
+    }
+
+    #[test]
+    fn plain_images_have_no_payload() {
+        let bytes = std::fs::read("images/pixel.webp").unwrap();
+
+        assert_eq!(super::Webp.extract(&bytes), Ok(None));
+    }
+
+    #[test]
+    fn embeds_and_strips_odd_payloads() {
+        let bytes = std::fs::read("images/pixel.webp").unwrap();
+
+        let embedded = super::Webp.embed(&bytes, b"payload").unwrap();
+        let chunks = super::chunks(&embedded).unwrap();
+
+        assert_eq!(
+            super::Webp.extract(&embedded),
+            Ok(Some(b"payload".to_vec()))
+        );
+        // Converted to the extended format, 1x1 with alpha
+        assert_eq!(&chunks[0].kind, b"VP8X");
+        assert_eq!(
+            &embedded[chunks[0].data.clone()],
+            &[0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(super::Webp.end(&embedded), Ok(embedded.len()));
+
+        // Converted back to the very same simple file
+        assert_eq!(super::Webp.strip(&embedded), Ok(bytes.clone()));
+        assert_eq!(
+            super::Webp.strip(&super::Webp.embed(&embedded, b"again").unwrap()),
+            Ok(bytes)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "File is not a valid WebP: it is truncated!")]
+    fn rejects_truncated_images() {
+        let bytes = std::fs::read("images/pixel.webp").unwrap();
+
+        crate::or_exit!(super::chunks(&bytes[..bytes.len() - 2]));
+    }
+}